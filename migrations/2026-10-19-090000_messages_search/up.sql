@@ -0,0 +1,2 @@
+-- Full text search over the stored messages, 'simple' as we mix french and english
+CREATE INDEX messages_content_search ON messages USING GIN (to_tsvector('simple', content));
@@ -0,0 +1,8 @@
+-- channel_id NULL is the guild default, keep_days NULL keeps the messages forever
+CREATE TABLE retention_policies (
+  id SERIAL PRIMARY KEY,
+  guild_id BIGINT NOT NULL,
+  channel_id BIGINT,
+  keep_days INT,
+  store BOOLEAN NOT NULL DEFAULT TRUE
+);
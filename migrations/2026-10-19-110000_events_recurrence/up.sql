@@ -0,0 +1,3 @@
+-- Recurrence rule of repeating events, NULL for one-shot events
+ALTER TABLE events
+ ADD recurrence VARCHAR;
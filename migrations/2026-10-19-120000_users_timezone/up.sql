@@ -0,0 +1,3 @@
+-- IANA time zone of the user, NULL for the guild default (Europe/Paris)
+ALTER TABLE users
+ ADD timezone VARCHAR;
@@ -0,0 +1,6 @@
+ALTER TABLE events
+ DROP COLUMN message_link;
+ALTER TABLE events
+ DROP COLUMN dm;
+ALTER TABLE events
+ DROP COLUMN mention;
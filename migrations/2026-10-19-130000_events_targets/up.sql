@@ -0,0 +1,11 @@
+-- Mention sent with the event, empty for the ones posted to a channel without ping
+ALTER TABLE events
+ ADD mention VARCHAR NOT NULL DEFAULT '';
+UPDATE events
+ SET mention = '<@' || author || '>';
+-- Deliver the event by direct message to the mentioned user instead of in the channel
+ALTER TABLE events
+ ADD dm BOOLEAN NOT NULL DEFAULT FALSE;
+-- Link of the message the event is about
+ALTER TABLE events
+ ADD message_link VARCHAR;
@@ -0,0 +1,3 @@
+-- One-shot events are kept a while after being sent so they can be snoozed
+ALTER TABLE events
+ ADD fired BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,18 @@
+CREATE TABLE announcements (
+  id SERIAL PRIMARY KEY,
+  author BIGINT NOT NULL,
+  channel BIGINT NOT NULL,
+  content VARCHAR NOT NULL,
+  title VARCHAR,
+  embed BOOLEAN NOT NULL DEFAULT FALSE,
+  trigger_date TIMESTAMP NOT NULL,
+  recurrence VARCHAR
+);
+
+-- Files are copied when scheduling, the discord links of the attachments expire
+CREATE TABLE announcement_files (
+  id SERIAL PRIMARY KEY,
+  announcement_id INT NOT NULL REFERENCES announcements (id) ON DELETE CASCADE,
+  filename VARCHAR NOT NULL,
+  data BYTEA NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Days out of office of the users, both dates included
+CREATE TABLE absences (
+  id SERIAL PRIMARY KEY,
+  user_id BIGINT NOT NULL,
+  start_date DATE NOT NULL,
+  end_date DATE NOT NULL,
+  reason VARCHAR
+);
+
+CREATE INDEX absences_dates ON absences (start_date, end_date);
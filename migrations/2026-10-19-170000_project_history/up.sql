@@ -0,0 +1,12 @@
+-- Changes made to the fiche of a project after its creation
+CREATE TABLE project_history (
+  id SERIAL PRIMARY KEY,
+  project_id INT NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+  author BIGINT NOT NULL,
+  field VARCHAR NOT NULL,
+  old_value VARCHAR NOT NULL,
+  new_value VARCHAR NOT NULL,
+  date TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX project_history_project ON project_history (project_id);
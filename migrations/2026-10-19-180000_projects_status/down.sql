@@ -0,0 +1,3 @@
+ALTER TABLE projects
+  DROP COLUMN status,
+  DROP COLUMN deleted_at;
@@ -0,0 +1,4 @@
+-- Lifecycle of the projects, the deleted ones are removed for good after a grace period
+ALTER TABLE projects
+  ADD COLUMN status VARCHAR NOT NULL DEFAULT 'active',
+  ADD COLUMN deleted_at TIMESTAMP;
@@ -0,0 +1,22 @@
+-- Deadline of the projects as a date, `deadline` keeps the text written at the creation
+ALTER TABLE projects ADD COLUMN deadline_date DATE;
+
+-- Deadlines that are not a real date, such as 31/02, are left NULL
+DO $$
+DECLARE
+  project RECORD;
+BEGIN
+  FOR project IN SELECT id, deadline FROM projects LOOP
+    BEGIN
+      IF project.deadline ~ '^\d{1,2}/\d{1,2}/\d{4}$' THEN
+        UPDATE projects SET deadline_date = TO_DATE(project.deadline, 'DD/MM/YYYY')
+          WHERE id = project.id;
+      ELSIF project.deadline ~ '^\d{4}-\d{2}-\d{2}$' THEN
+        UPDATE projects SET deadline_date = TO_DATE(project.deadline, 'YYYY-MM-DD')
+          WHERE id = project.id;
+      END IF;
+    EXCEPTION WHEN datetime_field_overflow OR invalid_datetime_format THEN
+      NULL;
+    END;
+  END LOOP;
+END $$;
@@ -0,0 +1,9 @@
+-- Members of the projects, kept in sync with the permission overwrites of their channel
+CREATE TABLE project_members (
+  id SERIAL PRIMARY KEY,
+  project_id INT NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+  user_id BIGINT NOT NULL,
+  source VARCHAR NOT NULL,
+  joined_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  UNIQUE (project_id, user_id)
+);
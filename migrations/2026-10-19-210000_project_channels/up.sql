@@ -0,0 +1,7 @@
+-- Channels created for a project by its template, besides its main channel
+CREATE TABLE project_channels (
+  id SERIAL PRIMARY KEY,
+  project_id INT NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+  channel_id BIGINT NOT NULL UNIQUE,
+  kind VARCHAR NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP TABLE project_access_requests;
+ALTER TABLE projects
+  DROP COLUMN access;
@@ -0,0 +1,15 @@
+-- Who can join a project: anyone, after the approval of its lead, or nobody
+ALTER TABLE projects
+  ADD COLUMN access VARCHAR NOT NULL DEFAULT 'open';
+
+-- Requests to join the projects that need an approval, with the decision taken
+CREATE TABLE project_access_requests (
+  id SERIAL PRIMARY KEY,
+  project_id INT NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+  user_id BIGINT NOT NULL,
+  source VARCHAR NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'pending',
+  requested_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  decided_by BIGINT,
+  decided_at TIMESTAMP
+);
@@ -0,0 +1,243 @@
+//! rbot-admin manage the bot state directly in the database, without going through discord.
+//!
+//! It's meant to fix things when the bot is down or when a command is broken, run `rbot-admin help` for the usage.
+
+use chrono::Utc;
+use rbot_discord::{
+  core::date_parse::DEFAULT_TIMEZONE,
+  database::{Dump, Instance, NewStorage, ProjectChangeset, Role, StorageDataType},
+  features::project_manager::set_deadline_date,
+};
+use std::{env, error::Error, fs, io, str::FromStr};
+
+type AdminResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+const USAGE: &str = "Usage: rbot-admin <COMMAND>
+
+Commands:
+  users list
+  users set-role <discord_id> <Guest|User|Moderator|Admin>
+  blocked list
+  blocked add <discord_id>
+  blocked remove <discord_id>
+  reminders list
+  reminders cancel <event_id>
+  projects list
+  projects edit <project_id> <field=value>... (fields: codex, client, lead, deadline, description, contexte)
+  invites list
+  invites edit <code> [channel=<channel_id>] [role=<role_id>] [count=<used_count>]
+  export [<file>]   Export the bot state as JSON, on stdout if no file is given
+  import <file>     Replace the bot state with a JSON export";
+
+fn main() {
+  rbot_discord::init();
+
+  let args: Vec<String> = env::args().skip(1).collect();
+  let args: Vec<&str> = args.iter().map(String::as_str).collect();
+  if matches!(args.first(), None | Some(&"help")) {
+    println!("{}", USAGE);
+    return;
+  }
+
+  let mut db_instance = Instance::new();
+  let result = match args.as_slice() {
+    ["users", "list"] => users_list(&db_instance),
+    ["users", "set-role", discord_id, role] => users_set_role(&mut db_instance, discord_id, role),
+    ["blocked", "list"] => blocked_list(&db_instance),
+    ["blocked", "add", discord_id] => blocked_add(&mut db_instance, discord_id),
+    ["blocked", "remove", discord_id] => blocked_remove(&mut db_instance, discord_id),
+    ["reminders", "list"] => reminders_list(&db_instance),
+    ["reminders", "cancel", event_id] => reminders_cancel(&mut db_instance, event_id),
+    ["projects", "list"] => projects_list(&db_instance),
+    ["projects", "edit", project_id, fields @ ..] if !fields.is_empty() => {
+      projects_edit(&mut db_instance, project_id, fields)
+    }
+    ["invites", "list"] => invites_list(&db_instance),
+    ["invites", "edit", code, fields @ ..] => invites_edit(&mut db_instance, code, fields),
+    ["export"] => export(&db_instance, None),
+    ["export", file] => export(&db_instance, Some(file)),
+    ["import", file] => import(&mut db_instance, file),
+    _ => Err(format!("Invalid command: {}\n\n{}", args.join(" "), USAGE).into()),
+  };
+
+  if let Err(error) = result {
+    eprintln!("{}", error);
+    std::process::exit(1);
+  }
+}
+
+fn parse_id<T: FromStr>(id: &str) -> Result<T, String> {
+  id.parse().map_err(|_| format!("Invalid id: {}", id))
+}
+
+fn users_list(db_instance: &Instance) -> AdminResult {
+  for user in &db_instance.users {
+    println!(
+      "{}\t{}\t{}\t{}",
+      user.id,
+      user.discordid,
+      user.role,
+      user.timezone.as_deref().unwrap_or("default timezone")
+    );
+  }
+  Ok(())
+}
+
+fn users_set_role(db_instance: &mut Instance, discord_id: &str, role: &str) -> AdminResult {
+  let role = Role::from_str(role).map_err(|_| format!("Role not found: {}", role))?;
+  println!(
+    "{}",
+    db_instance.user_role_update(parse_id(discord_id)?, role)
+  );
+  Ok(())
+}
+
+fn blocked_list(db_instance: &Instance) -> AdminResult {
+  for blocked in db_instance.filter_storage_type(StorageDataType::Blocked) {
+    match blocked.date {
+      Some(until) => println!(
+        "{}\tuntil {} UTC",
+        blocked.dataid.unwrap_or_default(),
+        chrono::DateTime::<chrono::Utc>::from(until).format("%d/%m/%Y %H:%M")
+      ),
+      None => println!("{}", blocked.dataid.unwrap_or_default()),
+    }
+  }
+  Ok(())
+}
+
+fn blocked_add(db_instance: &mut Instance, discord_id: &str) -> AdminResult {
+  let discord_id: i64 = parse_id(discord_id)?;
+  db_instance.storage_add(NewStorage {
+    date: None,
+    dataid: Some(discord_id),
+    datatype: StorageDataType::Blocked.into(),
+    data: "",
+  });
+  println!("Blocked {}", discord_id);
+  Ok(())
+}
+
+fn blocked_remove(db_instance: &mut Instance, discord_id: &str) -> AdminResult {
+  let discord_id: i64 = parse_id(discord_id)?;
+  let storage_ids = db_instance
+    .filter_storage_type(StorageDataType::Blocked)
+    .iter()
+    .filter(|blocked| blocked.dataid == Some(discord_id))
+    .map(|blocked| blocked.id)
+    .collect();
+  let removed = db_instance.storage_delete(storage_ids);
+  if removed.is_empty() {
+    return Err(format!("{} isn't blocked", discord_id).into());
+  }
+  println!("Unblocked {}", discord_id);
+  Ok(())
+}
+
+fn reminders_list(db_instance: &Instance) -> AdminResult {
+  for event in &db_instance.events {
+    println!(
+      "{}\t{} UTC\t{}\tauthor: {}\tchannel: {}\tmention: {}{}\t{}",
+      event.id,
+      event.trigger_date,
+      event.recurrence.as_deref().unwrap_or("once"),
+      event.author,
+      event.channel,
+      event.mention,
+      if event.dm { " (dm)" } else { "" },
+      event.content
+    );
+  }
+  Ok(())
+}
+
+fn reminders_cancel(db_instance: &mut Instance, event_id: &str) -> AdminResult {
+  let event_id: i32 = parse_id(event_id)?;
+  if !db_instance.events.iter().any(|event| event.id == event_id) {
+    return Err(format!("Reminder {} not found", event_id).into());
+  }
+  db_instance.event_delete(event_id);
+  println!("Cancelled reminder {}", event_id);
+  Ok(())
+}
+
+fn projects_list(db_instance: &Instance) -> AdminResult {
+  for project in &db_instance.projects {
+    println!(
+      "{}\tchannel: {}\tcodex: {}\tclient: {}\tlead: {}\tdeadline: {}",
+      project.id, project.channel_id, project.codex, project.client, project.lead, project.deadline
+    );
+  }
+  Ok(())
+}
+
+fn projects_edit(db_instance: &mut Instance, project_id: &str, fields: &[&str]) -> AdminResult {
+  let project_id: i32 = parse_id(project_id)?;
+  let mut changes = ProjectChangeset::default();
+  for field in fields {
+    let (name, value) = field
+      .split_once('=')
+      .ok_or_else(|| format!("Expected field=value, got: {}", field))?;
+    let target = match name {
+      "codex" => &mut changes.codex,
+      "client" => &mut changes.client,
+      "lead" => &mut changes.lead,
+      "deadline" => &mut changes.deadline,
+      "description" => &mut changes.description,
+      "contexte" => &mut changes.contexte,
+      _ => return Err(format!("Invalid field: {}", name).into()),
+    };
+    *target = Some(value);
+  }
+  set_deadline_date(
+    &mut changes,
+    Utc::now().with_timezone(&DEFAULT_TIMEZONE).date_naive(),
+  );
+  let project = db_instance.project_update(project_id, changes)?;
+  println!("Updated project {}: {:?}", project_id, project);
+  Ok(())
+}
+
+fn invites_list(db_instance: &Instance) -> AdminResult {
+  for invite in &db_instance.invites {
+    println!(
+      "{}\tused: {}\tchannel: {:?}\trole: {:?}",
+      invite.code, invite.used_count, invite.actionchannel, invite.actionrole
+    );
+  }
+  Ok(())
+}
+
+fn invites_edit(db_instance: &mut Instance, code: &str, fields: &[&str]) -> AdminResult {
+  let (mut count, mut channel, mut role) = (None, None, None);
+  for field in fields {
+    match field.split_once('=') {
+      Some(("count", value)) => count = Some(parse_id(value)?),
+      Some(("channel", value)) => channel = Some(parse_id(value)?),
+      Some(("role", value)) => role = Some(parse_id(value)?),
+      _ => return Err(format!("Invalid field: {}", field).into()),
+    }
+  }
+  let (_, invite) = db_instance.invite_update(code.to_string(), count, channel, role)?;
+  println!("{:?}", invite);
+  Ok(())
+}
+
+fn export(db_instance: &Instance, file: Option<&str>) -> AdminResult {
+  let dump = db_instance.dump()?;
+  match file {
+    Some(file) => {
+      serde_json::to_writer_pretty(fs::File::create(file)?, &dump)?;
+      eprintln!("Exported to {}", file);
+    }
+    None => serde_json::to_writer_pretty(io::stdout(), &dump)?,
+  }
+  Ok(())
+}
+
+fn import(db_instance: &mut Instance, file: &str) -> AdminResult {
+  let dump: Dump = serde_json::from_reader(fs::File::open(file)?)?;
+  db_instance.restore(dump)?;
+  println!("Imported {}", file);
+  Ok(())
+}
@@ -0,0 +1,101 @@
+//! French public holidays and company closure days, to schedule on business days only.
+use std::time::SystemTime;
+
+use crate::database::{StorageDataType, INSTANCE};
+use chrono::{prelude::*, Duration};
+
+/// Easter sunday of the gregorian calendar (anonymous gregorian algorithm).
+pub fn easter(year: i32) -> NaiveDate {
+  let a = year % 19;
+  let b = year / 100;
+  let c = year % 100;
+  let d = b / 4;
+  let e = b % 4;
+  let f = (b + 8) / 25;
+  let g = (b - f + 1) / 3;
+  let h = (19 * a + b - d - g + 15) % 30;
+  let i = c / 4;
+  let k = c % 4;
+  let l = (32 + 2 * e + 2 * i - h - k) % 7;
+  let m = (a + 11 * h + 22 * l) / 451;
+  let month = (h + l - 7 * m + 114) / 31;
+  let day = (h + l - 7 * m + 114) % 31 + 1;
+  NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+/// French public holidays of a year, sorted by date.
+pub fn french_holidays(year: i32) -> Vec<(NaiveDate, &'static str)> {
+  let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+  let easter = easter(year);
+  let mut holidays = vec![
+    (date(1, 1), "Jour de l'an"),
+    (easter + Duration::days(1), "Lundi de Pâques"),
+    (date(5, 1), "Fête du Travail"),
+    (date(5, 8), "Victoire 1945"),
+    (easter + Duration::days(39), "Ascension"),
+    (easter + Duration::days(50), "Lundi de Pentecôte"),
+    (date(7, 14), "Fête nationale"),
+    (date(8, 15), "Assomption"),
+    (date(11, 1), "Toussaint"),
+    (date(11, 11), "Armistice 1918"),
+    (date(12, 25), "Noël"),
+  ];
+  holidays.sort();
+  holidays
+}
+
+/// Name of the public holiday on this date.
+pub fn holiday(date: NaiveDate) -> Option<&'static str> {
+  french_holidays(date.year())
+    .into_iter()
+    .find(|(holiday, _)| *holiday == date)
+    .map(|(_, name)| name)
+}
+
+/// Company closure days added with the `closure` command, and their label.
+pub fn closure_days() -> Vec<(NaiveDate, String)> {
+  let db_instance = INSTANCE.read().unwrap();
+  db_instance
+    .filter_storage_type(StorageDataType::ClosureDay)
+    .into_iter()
+    .filter_map(|closure| Some((storage_date(closure.date?), closure.data.clone())))
+    .collect()
+}
+
+/// Closure days are stored at midnight UTC.
+pub fn storage_date(date: SystemTime) -> NaiveDate {
+  DateTime::<Utc>::from(date).date_naive()
+}
+
+pub fn is_business_day(date: NaiveDate, closures: &[NaiveDate]) -> bool {
+  !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+    && holiday(date).is_none()
+    && !closures.contains(&date)
+}
+
+/// First business day strictly after `date`.
+pub fn next_business_day(date: NaiveDate, closures: &[NaiveDate]) -> NaiveDate {
+  let mut next = date + Duration::days(1);
+  while !is_business_day(next, closures) {
+    next += Duration::days(1);
+  }
+  next
+}
+
+#[test]
+fn test_french_holidays() {
+  assert_eq!(easter(2024), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+  assert_eq!(easter(2025), NaiveDate::from_ymd_opt(2025, 4, 20).unwrap());
+  assert_eq!(easter(2026), NaiveDate::from_ymd_opt(2026, 4, 5).unwrap());
+  let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+  assert_eq!(holiday(date(4, 21)), Some("Lundi de Pâques"));
+  assert_eq!(holiday(date(5, 29)), Some("Ascension"));
+  assert_eq!(holiday(date(6, 9)), Some("Lundi de Pentecôte"));
+  assert_eq!(holiday(date(6, 10)), None);
+
+  // Friday 2025-05-02 => monday 5, with the 5 closed => tuesday 6
+  assert_eq!(next_business_day(date(5, 2), &[]), date(5, 5));
+  assert_eq!(next_business_day(date(5, 2), &[date(5, 5)]), date(5, 6));
+  // Wednesday 2025-04-30 => thursday 1st of may is a holiday
+  assert_eq!(next_business_day(date(4, 30), &[]), date(5, 2));
+}
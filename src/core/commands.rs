@@ -1,16 +1,24 @@
 //! Handle the connection with discord and it's events.
-use std::{collections::HashMap, error::Error, fmt::Write, process, str::FromStr};
+use std::{
+  collections::HashMap, error::Error, fmt::Write, process, str::FromStr, time::SystemTime,
+};
 
-use super::{parse, slash_command};
+use super::{
+  date_parse, parse,
+  scheduler::{self, Job},
+  slash_command,
+};
 use crate::features::calendar::check_calendar;
-use crate::features::{anyone::anyone, gemini};
 use crate::features::{
-  archivage, emoji, funny, invite_action, ordering, project_manager, renaming,
+  absences, announcements, archivage, emoji, funny, holidays, ics, invite_action, message_history,
+  ordering, personal_data, project_manager, renaming, retention, search, timezone,
 };
+use crate::features::{anyone::anyone, gemini};
 use crate::{
   database::{NewStorage, Role, StorageDataType, INSTANCE},
   features::events,
 };
+use chrono::Utc;
 use procedural_macros::command;
 use serenity::{futures::future::BoxFuture, FutureExt};
 use serenity::{
@@ -87,6 +95,15 @@ lazy_static! {
       usage: "@BOT quit",
       permission: Role::Admin,
     },
+    "announce" =>
+    Command {
+      exec: announcements::announce,
+      argument_min: 1,
+      argument_max: 6,
+      channel: None,
+      usage: "@BOT announce <#channel> <WHEN> <CONTENT> [embed] [title=<TITLE>] | list | cancel <id> | preview <id>",
+      permission: Role::User,
+    },
     "send_message" =>
     Command {
       exec: manual_send_message,
@@ -145,18 +162,18 @@ lazy_static! {
     Command {
       exec: project_manager::create,
       argument_min: 1,
-      argument_max: 7,
+      argument_max: 8,
       channel: None,
-      usage: "@BOT create-project <name> [codex=<codex>, client=<client>, lead=<Lead>, deadline=<Deadline>, description=<Brief projet>, contexte=<Contexte>]",
+      usage: "@BOT create-project <name> [codex=<codex>, client=<client>, lead=<Lead>, deadline=<Deadline>, description=<Brief projet>, contexte=<Contexte>, template=<Template>]",
       permission: Role::User,
     },
     "add-project" =>
     Command {
       exec: project_manager::add,
       argument_min: 2,
-      argument_max: 8,
+      argument_max: 9,
       channel: None,
-      usage: "@BOT add-project <#channel_id> <name> [codex=<codex>, client=<client>, lead=<Lead>, deadline=<Deadline>, description=<Brief projet>, contexte=<Contexte>]",
+      usage: "@BOT add-project <#channel_id> <name> [codex=<codex>, client=<client>, lead=<Lead>, deadline=<Deadline>, description=<Brief projet>, contexte=<Contexte>, template=<Template>]",
       permission: Role::User,
     },
     "delete-project" =>
@@ -165,7 +182,34 @@ lazy_static! {
       argument_min: 1,
       argument_max: 1,
       channel: None,
-      usage: "@BOT delete-project <name>",
+      usage: "@BOT delete-project <#channel>",
+      permission: Role::User,
+    },
+    "deadlines" =>
+    Command {
+      exec: project_manager::deadlines,
+      argument_min: 0,
+      argument_max: 6,
+      channel: None,
+      usage: "@BOT deadlines [overdue] | reminders [<DAYS>...]",
+      permission: Role::User,
+    },
+    "project" =>
+    Command {
+      exec: project_manager::project,
+      argument_min: 1,
+      argument_max: 8,
+      channel: None,
+      usage: "@BOT project edit [#channel] <field>=<value>... | history [#channel] | archive|hold|restore [#channel] | members [#channel]|check|sync | access [#channel] [open|approval|closed] | template list|show|set|remove | directory | migrate-fiches",
+      permission: Role::User,
+    },
+    "projects" =>
+    Command {
+      exec: project_manager::projects,
+      argument_min: 0,
+      argument_max: 4,
+      channel: None,
+      usage: "@BOT projects [client=<CLIENT>] [lead=<LEAD>] [codex=<CODEX>] [active|archived]",
       permission: Role::User,
     },
     "add" =>
@@ -226,9 +270,27 @@ lazy_static! {
     Command {
       exec: events::remind_me,
       argument_min: 2,
-      argument_max: 2,
+      argument_max: 4,
       channel: None,
-      usage: "@BOT remindme <WHEN ex: 1minute,1m,10h,5days> <CONTENT>",
+      usage: "@BOT remindme <WHEN ex: 10m,\"in 1h30\",\"tomorrow 9h\",\"lundi prochain 14h\",\"2026-11-03 10:00\",\"every monday 9h\",\"cron 0 9 * * 1-5\"> <CONTENT> [dm] [<message link>]",
+      permission: Role::User,
+    },
+    "remind" =>
+    Command {
+      exec: events::remind,
+      argument_min: 3,
+      argument_max: 5,
+      channel: None,
+      usage: "@BOT remind <@user|@role|#channel|me> <WHEN> <CONTENT> [dm] [<message link>]",
+      permission: Role::User,
+    },
+    "reminders" =>
+    Command {
+      exec: events::reminders,
+      argument_min: 1,
+      argument_max: 4,
+      channel: None,
+      usage: "@BOT reminders list | cancel <id> | edit <id> [when=<WHEN>] [content=<CONTENT>]",
       permission: Role::User,
     },
     "attack" =>
@@ -289,9 +351,9 @@ lazy_static! {
     Command {
       exec: block_user,
       argument_min: 1,
-      argument_max: 1,
+      argument_max: 2,
       channel: None,
-      usage: "@BOT block <user>",
+      usage: "@BOT block <user> [<UNTIL ex: 2h,\"tomorrow 9h\">]",
       permission: Role::Admin,
     },
     "help" =>
@@ -338,25 +400,128 @@ lazy_static! {
       channel: None,
       usage: "@BOT question \"What is the capital of France ?\"",
       permission: Role::User,
+    },
+    "search" =>
+    Command {
+      exec: search::search,
+      argument_min: 1,
+      argument_max: 20,
+      channel: None,
+      usage: "@BOT search <terms> [from:<@user>] [in:<#channel>] [before:<YYYY-MM-DD>] [after:<YYYY-MM-DD>] [page:<N>]",
+      permission: Role::User,
+    },
+    "history" =>
+    Command {
+      exec: message_history::history,
+      argument_min: 1,
+      argument_max: 1,
+      channel: None,
+      usage: "@BOT history <message link>",
+      permission: Role::Guest,
+    },
+    "mydata" =>
+    Command {
+      exec: personal_data::my_data,
+      argument_min: 1,
+      argument_max: 1,
+      channel: None,
+      usage: "@BOT mydata export (DM you everything stored about you)",
+      permission: Role::Guest,
+    },
+    "forget" =>
+    Command {
+      exec: personal_data::forget,
+      argument_min: 1,
+      argument_max: 1,
+      channel: None,
+      usage: "@BOT forget <@user> (erase everything stored about the user)",
+      permission: Role::Admin,
+    },
+    "retention" =>
+    Command {
+      exec: retention::retention,
+      argument_min: 0,
+      argument_max: 2,
+      channel: None,
+      usage: "@BOT retention [<#channel|guild> <forever|nostore|default|<N>d>]",
+      permission: Role::Admin,
+    },
+    "holidays" =>
+    Command {
+      exec: holidays::holidays,
+      argument_min: 0,
+      argument_max: 3,
+      channel: None,
+      usage: "@BOT holidays [<YEAR>] | add <DATE> [<LABEL>] | remove <DATE>",
+      permission: Role::User,
+    },
+    "ics" =>
+    Command {
+      exec: ics::ics,
+      argument_min: 0,
+      argument_max: 2,
+      channel: None,
+      usage: "@BOT ics [guild] | link [reset]",
+      permission: Role::User,
+    },
+    "ooo" =>
+    Command {
+      exec: absences::ooo,
+      argument_min: 1,
+      argument_max: 3,
+      channel: None,
+      usage: "@BOT ooo <FROM> <TO> [<REASON>] | list | cancel <id>",
+      permission: Role::User,
+    },
+    "who-is-out" =>
+    Command {
+      exec: absences::who_is_out,
+      argument_min: 0,
+      argument_max: 2,
+      channel: None,
+      usage: "@BOT who-is-out [<DATE>] | channel <#channel>|off",
+      permission: Role::User,
+    },
+    "timezone" =>
+    Command {
+      exec: timezone::timezone,
+      argument_min: 0,
+      argument_max: 2,
+      channel: None,
+      usage: "@BOT timezone [set <IANA name ex: Europe/Paris>|reset]",
+      permission: Role::User,
     }
   ];
 }
 
 #[command]
 async fn block_user(params: CallBackParams) -> CallbackReturn {
-  let mut db_instance = INSTANCE.write().unwrap();
-
   let user_id = match parse::discord_str_to_id(&params.args[1], Some(parse::DiscordIds::User)) {
     Ok((userid, _)) => userid,
     Err(error) => return Ok(Some(error)),
   };
+  // A block with a date is lifted by the scheduler at that date
+  let until = match params.args.get(2) {
+    Some(until) => {
+      let now = date_parse::user_now(params.message.author.id.0);
+      match date_parse::parse_date_time(until, now) {
+        Ok(until) => Some(until.with_timezone(&Utc)),
+        Err(error) => return Ok(Some(error)),
+      }
+    }
+    None => None,
+  };
 
-  db_instance.storage_add(NewStorage {
-    date: None,
+  let mut db_instance = INSTANCE.write().unwrap();
+  let blocked = db_instance.storage_add(NewStorage {
+    date: until.map(SystemTime::from),
     dataid: Some(user_id as i64),
     datatype: StorageDataType::Blocked as i64,
     data: "",
   });
+  if let Some(until) = until {
+    scheduler::schedule(until, Job::Unblock(blocked.id));
+  }
   Ok(Some(String::from(":ok:")))
 }
 
@@ -0,0 +1,329 @@
+//! Parse the dates and times written by users in commands, in french or english.
+//!
+//! Accepted expressions, words can be combined:
+//!  * durations: `10m`, `5h`, `1h30`, `in 2 days`, `dans 3 semaines`, `2d14h30` (in 2 days at 14:30)
+//!  * days: `today`, `tomorrow`, `demain`, `après-demain`, `lundi prochain`, `next friday`
+//!  * business days: `next business day`, `prochain jour ouvré`, skipping holidays and closures
+//!  * absolute dates: `2026-11-03`, `03/11/2026`, `03/11`
+//!  * times: `9h`, `14h30`, `10:00`, `3pm`, `at 9h`, `à 9h`
+//!
+//! A bare `5h` is a duration unless a day is given (`tomorrow 5h`).
+use super::business_days;
+use crate::database;
+use chrono::{prelude::*, Duration};
+use chrono_tz::{Europe::Paris, Tz};
+use regex::Regex;
+
+/// Time used when only a day is given.
+const DEFAULT_HOUR: u32 = 9;
+/// Time zone of the users that did not set their own.
+pub const DEFAULT_TIMEZONE: Tz = Paris;
+
+lazy_static! {
+  static ref DURATION_REGEX: Regex = Regex::new(
+    r#"^(?:([0-9]{1,4})(m|mins?|minutes?|h|hours?|heures?|d|days?|j|jours?|w|weeks?|semaines?))+([0-9]{2})?$"#
+  )
+  .expect("unable to create regex");
+  static ref DURATION_PART_REGEX: Regex =
+    Regex::new(r#"([0-9]{1,4})([a-z]+)"#).expect("unable to create regex");
+  static ref DAYS_AT_REGEX: Regex =
+    Regex::new(r#"^([0-9]{1,4})d(?:ays?)?([0-9]{2})[:h]([0-9]{2})?$"#)
+      .expect("unable to create regex");
+}
+
+pub const WEEKDAYS: [Weekday; 5] = [
+  Weekday::Mon,
+  Weekday::Tue,
+  Weekday::Wed,
+  Weekday::Thu,
+  Weekday::Fri,
+];
+pub const ALL_DAYS: [Weekday; 7] = [
+  Weekday::Mon,
+  Weekday::Tue,
+  Weekday::Wed,
+  Weekday::Thu,
+  Weekday::Fri,
+  Weekday::Sat,
+  Weekday::Sun,
+];
+
+/// English or french day names, and groups of days.
+pub fn parse_weekdays(input: &str) -> Option<Vec<Weekday>> {
+  let day = match input {
+    "monday" | "mon" | "lundi" => Weekday::Mon,
+    "tuesday" | "tue" | "mardi" => Weekday::Tue,
+    "wednesday" | "wed" | "mercredi" => Weekday::Wed,
+    "thursday" | "thu" | "jeudi" => Weekday::Thu,
+    "friday" | "fri" | "vendredi" => Weekday::Fri,
+    "saturday" | "sat" | "samedi" => Weekday::Sat,
+    "sunday" | "sun" | "dimanche" => Weekday::Sun,
+    "weekday" | "weekdays" | "semaine" => return Some(WEEKDAYS.to_vec()),
+    "day" | "jour" => return Some(ALL_DAYS.to_vec()),
+    _ => return None,
+  };
+  Some(vec![day])
+}
+
+/// Hours of the day like `9h`, `9h30`, `18:00`, `9`, `3pm` or `3:30pm`.
+pub fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+  let (input, pm) = match (input.strip_suffix("am"), input.strip_suffix("pm")) {
+    (Some(input), _) => (input, Some(false)),
+    (_, Some(input)) => (input, Some(true)),
+    _ => (input, None),
+  };
+  let (hours, minutes) = match input.split_once(['h', ':']) {
+    Some((hours, "")) => (hours, "0"),
+    Some((hours, minutes)) => (hours, minutes),
+    None => (input, "0"),
+  };
+  if hours.is_empty() || hours.len() > 2 || minutes.len() > 2 {
+    return None;
+  }
+  let mut hours: u32 = hours.parse().ok()?;
+  match pm {
+    Some(_) if hours == 0 || hours > 12 => return None,
+    Some(true) if hours < 12 => hours += 12,
+    Some(false) if hours == 12 => hours = 0,
+    _ => (),
+  }
+  NaiveTime::from_hms_opt(hours, minutes.parse().ok()?, 0)
+}
+
+/// Named or absolute day, `today` being the reference for relative ones.
+pub fn parse_day(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+  let offset = match input {
+    "today" | "aujourd'hui" | "aujourdhui" => Some(0),
+    "tomorrow" | "demain" => Some(1),
+    "après-demain" | "apres-demain" | "aprèsdemain" | "apresdemain" => Some(2),
+    "yesterday" | "hier" => Some(-1),
+    _ => None,
+  };
+  if let Some(offset) = offset {
+    return Some(today + Duration::days(offset));
+  }
+  if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+    return Some(date);
+  }
+  if let Ok(date) = NaiveDate::parse_from_str(input, "%d/%m/%Y") {
+    return Some(date);
+  }
+  // Day and month only: the next time this date happens
+  let (day, month) = input.split_once('/')?;
+  let (day, month) = (day.parse().ok()?, month.parse().ok()?);
+  let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+  if date < today {
+    return NaiveDate::from_ymd_opt(today.year() + 1, month, day);
+  }
+  Some(date)
+}
+
+fn parse_duration_unit(count: i64, unit: &str) -> Option<Duration> {
+  match unit {
+    "m" | "min" | "mins" | "minute" | "minutes" => Some(Duration::minutes(count)),
+    "h" | "hour" | "hours" | "heure" | "heures" => Some(Duration::hours(count)),
+    "d" | "day" | "days" | "j" | "jour" | "jours" => Some(Duration::days(count)),
+    "w" | "week" | "weeks" | "semaine" | "semaines" => Some(Duration::weeks(count)),
+    _ => None,
+  }
+}
+
+/// Durations in a single word: `10m`, `1h30`, `2d4h`.
+fn parse_duration(input: &str) -> Option<Duration> {
+  let captures = DURATION_REGEX.captures(input)?;
+  let mut duration = Duration::zero();
+  for part in DURATION_PART_REGEX.captures_iter(input) {
+    duration += parse_duration_unit(part[1].parse().ok()?, &part[2])?;
+  }
+  if let Some(minutes) = captures.get(3) {
+    // 1h30: the trailing number are the minutes of the hour unit
+    if !input[..minutes.start()].ends_with('h') {
+      return None;
+    }
+    duration += Duration::minutes(minutes.as_str().parse().ok()?);
+  }
+  Some(duration)
+}
+
+/// Convert a local time to a date of `timezone`, the times skipped by a daylight saving change are
+/// moved one hour later.
+pub fn from_local(timezone: Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+  timezone.from_local_datetime(&local).earliest().or_else(|| {
+    timezone
+      .from_local_datetime(&(local + Duration::hours(1)))
+      .earliest()
+  })
+}
+
+/// Parse a date and time expression relative to `now`, the result is in the future.
+pub fn parse_date_time(input: &str, now: DateTime<Tz>) -> Result<DateTime<Tz>, String> {
+  let input = input.trim().to_lowercase();
+  let words: Vec<&str> = input.split_whitespace().collect();
+  let today = now.date_naive();
+  let invalid = |word: &str| format!("I don't understand `{}` in `{}`", word, input);
+
+  let mut day = None;
+  let mut time = None;
+  let mut duration = Duration::zero();
+  // Words like 5h, a time of day when a day is given, a duration otherwise
+  let mut ambiguous = Vec::new();
+  let mut is_duration = false;
+  let mut is_time = false;
+
+  let mut index = 0;
+  while index < words.len() {
+    let word = words[index];
+    index += 1;
+    match word {
+      "in" | "dans" => is_duration = true,
+      "at" | "à" | "a" | "vers" => is_time = true,
+      "next" | "prochain" | "prochaine" | "on" | "le" | "and" | "et" => (),
+      "workday" | "business" | "working" | "jour"
+        if word == "workday" || matches!(words.get(index), Some(&("day" | "ouvré" | "ouvre"))) =>
+      {
+        if word != "workday" {
+          index += 1;
+        }
+        let closures: Vec<NaiveDate> = business_days::closure_days()
+          .into_iter()
+          .map(|(date, _)| date)
+          .collect();
+        day = Some(business_days::next_business_day(today, &closures));
+      }
+      _ => {
+        if let Some(captures) = DAYS_AT_REGEX.captures(word) {
+          let days: i64 = captures[1].parse().map_err(|_| invalid(word))?;
+          day = Some(today + Duration::days(days));
+          let minutes = captures.get(3).map_or("00", |minutes| minutes.as_str());
+          time = Some(
+            parse_time_of_day(&format!("{}h{}", &captures[2], minutes))
+              .ok_or_else(|| invalid(word))?,
+          );
+        } else if let Some(date) = parse_day(word, today) {
+          day = Some(date);
+        } else if let Some([weekday]) = parse_weekdays(word).as_deref() {
+          let days_until =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday() - 1) % 7
+              + 1;
+          day = Some(today + Duration::days(days_until as i64));
+        } else if let Ok(count) = word.parse::<i64>() {
+          // 2 days, 30 minutes
+          let unit = words.get(index).copied().unwrap_or_default();
+          match parse_duration_unit(count, unit) {
+            Some(unit_duration) => {
+              duration += unit_duration;
+              index += 1;
+            }
+            None if is_time => time = Some(parse_time_of_day(word).ok_or_else(|| invalid(word))?),
+            None => return Err(invalid(word)),
+          }
+        } else if let (Some(word_duration), Some(word_time)) =
+          (parse_duration(word), parse_time_of_day(word))
+        {
+          if is_time {
+            time = Some(word_time);
+          } else {
+            ambiguous.push((word_duration, word_time));
+          }
+        } else if let Some(word_duration) = parse_duration(word) {
+          duration += word_duration;
+        } else if let Some(word_time) = parse_time_of_day(word) {
+          time = Some(word_time);
+        } else {
+          return Err(invalid(word));
+        }
+      }
+    }
+  }
+
+  for (word_duration, word_time) in ambiguous {
+    if !is_duration && day.is_some() && time.is_none() {
+      time = Some(word_time);
+    } else {
+      duration += word_duration;
+    }
+  }
+
+  let date = match (day, time) {
+    (None, None) if duration.is_zero() => return Err(format!("`{}` is not a date", input)),
+    (None, None) => now,
+    (day, time) => {
+      let time = time.unwrap_or_else(|| NaiveTime::from_hms_opt(DEFAULT_HOUR, 0, 0).unwrap());
+      let mut local = day.unwrap_or(today).and_time(time);
+      // A time alone is the next time it happens
+      if day.is_none() && local <= now.naive_local() {
+        local += Duration::days(1);
+      }
+      from_local(now.timezone(), local).ok_or_else(|| format!("`{}` does not exist", input))?
+    }
+  } + duration;
+  if date <= now {
+    return Err(format!("{} is in the past", format_date_time(&date)));
+  }
+  Ok(date)
+}
+
+/// Time zone set by the user with the `timezone` command.
+pub fn user_timezone(user_id: u64) -> Tz {
+  let db_instance = database::INSTANCE.read().unwrap();
+  db_instance
+    .user_search(user_id)
+    .and_then(|user| user.timezone.as_deref())
+    .and_then(|timezone| timezone.parse().ok())
+    .unwrap_or(DEFAULT_TIMEZONE)
+}
+
+/// The current date and time for a user.
+pub fn user_now(user_id: u64) -> DateTime<Tz> {
+  Utc::now().with_timezone(&user_timezone(user_id))
+}
+
+/// Human readable date, used to confirm the dates parsed.
+pub fn format_date_time(date: &DateTime<Tz>) -> String {
+  date.format("%A %d/%m/%Y %H:%M").to_string()
+}
+
+/// Human readable date of a stored UTC time, in the time zone of a user.
+pub fn format_user_date_time(date: impl Into<DateTime<Utc>>, timezone: Tz) -> String {
+  date
+    .into()
+    .with_timezone(&timezone)
+    .format("%d/%m/%Y %H:%M %Z")
+    .to_string()
+}
+
+#[test]
+fn test_parse_date_time() {
+  // Friday 2024-03-01 18:00 in Paris
+  let now = Paris.with_ymd_and_hms(2024, 3, 1, 18, 0, 0).unwrap();
+  let at = |day, hour, minute| {
+    Paris
+      .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+      .unwrap()
+  };
+  for (input, expected) in [
+    ("10m", at(1, 18, 10)),
+    ("5h", at(1, 23, 0)),
+    ("2d14h30", at(3, 14, 30)),
+    ("2d", at(3, 18, 0)),
+    ("in 1h30", at(1, 19, 30)),
+    ("dans 2 jours", at(3, 18, 0)),
+    ("in 2 hours 30 minutes", at(1, 20, 30)),
+    ("tomorrow 9h", at(2, 9, 0)),
+    ("demain à 14h30", at(2, 14, 30)),
+    ("lundi prochain 14h", at(4, 14, 0)),
+    ("next friday", at(8, 9, 0)),
+    ("monday 3pm", at(4, 15, 0)),
+    ("2024-03-05 10:00", at(5, 10, 0)),
+    ("05/03 10:00", at(5, 10, 0)),
+    ("20:00", at(1, 20, 0)),
+    ("8h", at(2, 2, 0)),
+    ("at 8h", at(2, 8, 0)),
+  ] {
+    assert_eq!(parse_date_time(input, now), Ok(expected), "{}", input);
+  }
+  assert!(parse_date_time("yesterday", now).is_err());
+  assert!(parse_date_time("2023-01-01", now).is_err());
+  assert!(parse_date_time("someday", now).is_err());
+  assert!(parse_date_time("", now).is_err());
+}
@@ -10,7 +10,7 @@ use serenity::{
   async_trait,
   model::{
     application::interaction::Interaction,
-    channel::{Message, Reaction},
+    channel::{Channel, Message, Reaction},
     event::{MessageUpdateEvent, ResumedEvent},
     gateway::Ready,
     guild::Member,
@@ -29,7 +29,7 @@ use crate::{
   },
   features::{
     deployment::{DeploymentReactionsData, ValidationEmoji},
-    invite_action, mecleanup, project_manager, Features,
+    invite_action, mecleanup, project_manager, retention, Features,
   },
 };
 
@@ -51,9 +51,10 @@ impl EventHandler for Handler {
       message.timestamp, chan_name, message.author.name, message.content
     );
 
+    let parent = retention::thread_parent(&ctx, message.guild_id, message.channel_id);
     #[allow(clippy::needless_borrow)]
     // Here clippy is wrong, we actually need to ref message before calling into
-    database_update((&message).into(), false);
+    database_update((&message).into(), message.guild_id, parent, false);
     archive_activity(&ctx, &message).await;
     if message.is_own(&ctx) || message.content.is_empty() {
       return;
@@ -82,9 +83,10 @@ impl EventHandler for Handler {
       event_clone.author.unwrap_or_default().name,
       event_clone.content.unwrap_or_default(),
     );
+    let parent = retention::thread_parent(&ctx, event.guild_id, event.channel_id);
     #[allow(clippy::needless_borrow)]
     // Here clippy is wrong, we actually need to ref message before calling into
-    database_update((&event).into(), true);
+    database_update((&event).into(), event.guild_id, parent, true);
     let new_message = if let Some(message) = new {
       message
     } else {
@@ -151,6 +153,15 @@ impl EventHandler for Handler {
     }
   }
 
+  async fn channel_update(&self, ctx: Context, old: Option<Channel>, new: Channel) {
+    let (Some(Channel::Guild(old)), Channel::Guild(new)) = (old, new) else {
+      return;
+    };
+    if old.name != new.name && project_manager::is_project_channel(new.id) {
+      project_manager::refresh_directory(&ctx.http).await;
+    }
+  }
+
   async fn guild_member_addition(&self, ctx: Context, mut new_member: Member) {
     invite_action::on_new_member_check(ctx, &mut new_member).await;
   }
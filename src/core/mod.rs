@@ -1,10 +1,13 @@
 //! The base of the program containing the abstractions for files and connection to discord.
 
+pub mod business_days;
 pub mod commands;
+pub mod date_parse;
 pub mod eventhandler;
 pub mod parse;
 pub mod permissions;
 pub mod process;
+pub mod scheduler;
 pub mod slash_command;
 pub mod validation;
 
@@ -44,6 +44,39 @@ pub async fn get_guild(
   }
 }
 
+/// Link that jumps to a message in the discord client.
+pub fn message_url(guild_id: u64, channel_id: u64, message_id: u64) -> String {
+  format!(
+    "https://discord.com/channels/{}/{}/{}",
+    guild_id, channel_id, message_id
+  )
+}
+
+lazy_static! {
+  static ref MESSAGE_URL_REGEX: Regex = Regex::new(
+    r#"^<?https://(?:\w+\.)?discord(?:app)?\.com/channels/(?<guild>[0-9]+|@me)/(?<channel>[0-9]+)/(?<message>[0-9]+)>?$"#
+  )
+  .unwrap();
+}
+
+/// Extract the guild (None for private channels), channel and message ids from a message link.
+pub fn message_url_to_ids(url: &str) -> Result<(Option<u64>, u64, u64), String> {
+  let Some(captures) = MESSAGE_URL_REGEX.captures(url) else {
+    return Err(format!("Not a message link: {}", url));
+  };
+  // Ids are already validated by the regex, except for their size.
+  let parse_id = |name: &str| {
+    captures[name]
+      .parse::<u64>()
+      .map_err(|_| format!("Invalid id in message link: {}", url))
+  };
+  let guild = match &captures["guild"] {
+    "@me" => None,
+    _ => Some(parse_id("guild")?),
+  };
+  Ok((guild, parse_id("channel")?, parse_id("message")?))
+}
+
 lazy_static! {
   static ref DISCORD_IDS_REGEX: Regex =
     Regex::new(r#"<(?<type>@!?|#|@&)(?<id>[0-9]{18,24})>"#).unwrap();
@@ -126,6 +159,27 @@ fn test_discord_str_to_id() {
   );
 }
 
+#[test]
+fn test_message_url_to_ids() {
+  assert_eq!(
+    message_url_to_ids(
+      "https://discord.com/channels/464779118857420811/747066293135605791/1096007878160154697"
+    ),
+    Ok((
+      Some(464779118857420811),
+      747066293135605791,
+      1096007878160154697
+    ))
+  );
+  assert_eq!(
+    message_url_to_ids(
+      "<https://ptb.discordapp.com/channels/@me/747066293135605791/1096007878160154697>"
+    ),
+    Ok((None, 747066293135605791, 1096007878160154697))
+  );
+  assert!(message_url_to_ids("https://discord.com/channels/464779118857420811").is_err());
+}
+
 #[test]
 fn test_split_message_args() {
   assert_eq!(
@@ -205,3 +259,44 @@ pub fn emoji_str_convert(emoji_str: &str) -> Option<(bool, &str, &str)> {
     )
   })
 }
+
+/// Maximum number of characters of a Discord message.
+pub const MESSAGE_LIMIT: usize = 2000;
+
+/// Split a reply in messages of at most [MESSAGE_LIMIT] characters, between lines when possible.
+pub fn split_reply(reply: &str) -> Vec<String> {
+  let mut messages = Vec::new();
+  let mut message = String::new();
+  let mut length = 0;
+  for line in reply.split_inclusive('\n') {
+    for chunk in line.chars().collect::<Vec<char>>().chunks(MESSAGE_LIMIT) {
+      if length + chunk.len() > MESSAGE_LIMIT {
+        messages.push(std::mem::take(&mut message));
+        length = 0;
+      }
+      message.extend(chunk);
+      length += chunk.len();
+    }
+  }
+  messages.push(message);
+  messages
+    .into_iter()
+    .map(|message| message.trim_end().to_string())
+    .filter(|message| !message.is_empty())
+    .collect()
+}
+
+#[test]
+fn test_split_reply() {
+  assert!(split_reply("").is_empty());
+  assert_eq!(split_reply("a\nb\n"), vec!["a\nb"]);
+  let line = "x".repeat(1500);
+  let reply = format!("{}\n{}\n{}", line, line, "y".repeat(4500));
+  let messages = split_reply(&reply);
+  assert_eq!(messages.len(), 5);
+  assert_eq!(messages[0], line);
+  assert_eq!(messages[1], line);
+  assert!(messages
+    .iter()
+    .all(|message| message.chars().count() <= MESSAGE_LIMIT));
+}
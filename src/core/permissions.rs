@@ -59,6 +59,15 @@ pub async fn is_user_allowed(
   (dbrole >= expected, dbrole)
 }
 
+/// Role of the user as stored in the database, without checking the discord roles.
+pub fn user_role(user_id: UserId) -> database::Role {
+  let db_instance = database::INSTANCE.read().unwrap();
+  db_instance
+    .user_search(user_id.0)
+    .and_then(|user| database::Role::from_str(&user.role).ok())
+    .unwrap_or(database::Role::Guest)
+}
+
 pub enum ReadState {
   Allow,
   Deny,
@@ -3,14 +3,14 @@ use super::commands::{
   CallBackParams, COMMANDS_LIST, CONTAIN_MSG_LIST, CONTAIN_REACTION_LIST, TAG_MSG_LIST,
 };
 use super::permissions;
-use crate::core::parse::split_message_args;
+use crate::core::parse::{split_message_args, split_reply, DiscordIds};
 use crate::database;
-use crate::features::funny::ATTACKED;
+use crate::features::{absences, funny::ATTACKED, project_manager};
 use log::{debug, error};
 use serenity::model::event::MessageUpdateEvent;
 use serenity::{
   model::channel::Message,
-  model::id::{ChannelId, UserId},
+  model::id::{ChannelId, GuildId, UserId},
   prelude::*,
 };
 use std::process::exit;
@@ -27,6 +27,7 @@ pub async fn process_message(ctx: Context, message: Message) {
   personal_attack(&ctx, &message).await;
   annoy_channel(&ctx, &message).await;
   filter_outannoying_messages(&ctx, &message).await;
+  absences::out_of_office_reply(&ctx, &message).await;
 
   //Check if i am tagged in the message else do the reactions
   // check for @me first so it's considered a command
@@ -135,11 +136,8 @@ pub async fn process_command(message_split: &[String], message: &Message, ctx: &
           if reply == ":ok:" {
             message.react(&ctx.http, '✅').await.unwrap();
           } else {
-            for chunk in reply.chars().collect::<Vec<char>>().chunks(1900) {
-              message
-                .reply(&ctx.http, chunk.iter().collect::<String>())
-                .await
-                .unwrap();
+            for chunk in split_reply(&reply) {
+              message.reply(&ctx.http, chunk).await.unwrap();
             }
           }
         }
@@ -266,9 +264,11 @@ pub async fn is_user_blocked(_: &Context, message: &Message) -> bool {
   let db_instance = database::INSTANCE.read().unwrap();
   let blocked_users = db_instance.filter_storage_type(database::StorageDataType::Blocked);
   let user_id = message.author.id.0;
-  return blocked_users
+  let now = SystemTime::now();
+  blocked_users
     .iter()
-    .any(|x| x.dataid.unwrap() == user_id as i64);
+    .filter(|x| x.date.is_none_or(|until| until > now))
+    .any(|x| x.dataid.unwrap() == user_id as i64)
 }
 
 impl From<&Message> for database::Message {
@@ -309,7 +309,15 @@ impl From<&MessageUpdateEvent> for database::Message {
   }
 }
 
-pub fn database_update(message: database::Message, is_edit: bool) {
+/// Store a new message or an edit, following the retention policy of its channel.
+///
+/// `parent` is the channel of the thread the message was posted in, if any.
+pub fn database_update(
+  message: database::Message,
+  guild_id: Option<GuildId>,
+  parent: Option<i64>,
+  is_edit: bool,
+) {
   let mut db_instance = match database::INSTANCE.write() {
     Ok(instance) => instance,
     Err(poison_error) => {
@@ -317,6 +325,9 @@ pub fn database_update(message: database::Message, is_edit: bool) {
       exit(1)
     }
   };
+  let store = db_instance
+    .retention_policy(guild_id.map(|id| id.0 as i64), message.channel, parent)
+    .is_none_or(|policy| policy.store);
 
   if is_edit
     && db_instance
@@ -324,6 +335,9 @@ pub fn database_update(message: database::Message, is_edit: bool) {
       .iter()
       .any(|db_message| message.id == db_message.id)
   {
+    if !store {
+      return;
+    }
     db_instance.message_edit_add(database::NewMessageEdit {
       id: None,
       parrent_message_id: message.id,
@@ -340,24 +354,48 @@ pub fn database_update(message: database::Message, is_edit: bool) {
     {
       db_instance.user_add(message.author, &database::Role::Guest.to_string());
     }
-    db_instance.message_add(message);
+    if store {
+      db_instance.message_add(message);
+    }
   }
 }
 
-// TODO: This is only working for 1 server as channel is static
-use crate::constants::discordids::{ARCHIVE_CATEGORY, PROJECT_CATEGORY};
+use crate::features::categories::{self, CategoryFamily};
 pub async fn archive_activity(ctx: &Context, message: &Message) {
   match message.channel(&ctx.http).await {
     Ok(channel) => {
       let channelid = channel.id().0;
       match channel.guild() {
-        Some(mut channel) => {
+        Some(channel) => {
           if let Some(category) = channel.parent_id {
-            if category == ARCHIVE_CATEGORY {
-              channel
-                .edit(&ctx.http, |edit| edit.category(ChannelId(PROJECT_CATEGORY)))
-                .await
-                .unwrap_or_else(|_| panic!("Unable to edit channel:{} to unarchive", channel.id));
+            if CategoryFamily::Archives.contains(category) {
+              let project = {
+                let db_instance = database::INSTANCE.read().unwrap();
+                db_instance
+                  .projects_search(channelid as i64, DiscordIds::Channel)
+                  .map(|(_, project)| project.clone())
+              };
+              match project {
+                // A deleted project stays archived until it is removed
+                Some(project) if project.status() == database::ProjectStatus::Deleted => (),
+                Some(project) => {
+                  if let Err(e) = project_manager::set_project_status(
+                    &ctx.http,
+                    &project,
+                    database::ProjectStatus::Active,
+                    message.author.id,
+                  )
+                  .await
+                  {
+                    error!("Unable to unarchive project {}: {}", project.id, e);
+                  }
+                }
+                None => categories::move_to_family(&ctx.http, channel.id, CategoryFamily::Projects)
+                  .await
+                  .unwrap_or_else(|e| {
+                    panic!("Unable to edit channel:{} to unarchive: {}", channel.id, e)
+                  }),
+              }
             }
           }
         }
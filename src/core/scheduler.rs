@@ -0,0 +1,194 @@
+//! Run the timed jobs of the bot at their due time.
+//!
+//! Jobs are kept in a priority queue ordered by due date, the scheduler sleeps until the next one
+//! and is woken up by [schedule] when an earlier job is added.
+//! The queue only holds ids: a job checks the database when it runs, so cancelled or rescheduled
+//! entries are simply skipped. Pending jobs are reloaded from the database on startup, the ones
+//! missed while the bot was offline run right away.
+use std::{cmp::Reverse, collections::BinaryHeap, sync::Arc, sync::Mutex};
+
+use crate::{
+  database::{StorageDataType, INSTANCE},
+  features::{absences, announcements, events, project_manager, retention},
+};
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serenity::http::Http;
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Job {
+  /// Send a reminder or delete it once sent, the id is the one of the `events` table
+  Event(i32),
+  /// Post a scheduled announcement, the id is the one of the `announcements` table
+  Announcement(i32),
+  /// Lift a temporary block, the id is the one of the `storage` entry
+  Unblock(i32),
+  /// Delete the messages past their retention
+  Retention,
+  /// Post who is out of office today
+  AbsenceSummary,
+  /// Remove a deleted project after its grace period, the id is the one of the `projects` table
+  ProjectDeletion(i32),
+  /// Remind the deadline of a project, the id of the `projects` table and the days before it
+  DeadlineReminder(i32, u32),
+  /// Post the deadlines of the week
+  DeadlineSummary,
+  /// Match the recorded project members with the channels
+  MemberReconciliation,
+}
+
+/// Minutes before a job that failed on a Discord error runs again.
+const RETRY_DELAY_MINUTES: i64 = 10;
+
+/// Jobs sorted by due date, the earliest first.
+type JobQueue = BinaryHeap<Reverse<(DateTime<Utc>, Job)>>;
+
+pub struct Scheduler {
+  queue: Mutex<JobQueue>,
+  wake_up: Notify,
+}
+
+lazy_static! {
+  static ref SCHEDULER: Scheduler = Scheduler {
+    queue: Mutex::new(BinaryHeap::new()),
+    wake_up: Notify::new(),
+  };
+}
+
+/// Queue a job to run at `date`, a date in the past runs it right away.
+///
+/// A job already queued is moved to the new date.
+pub fn schedule(date: DateTime<Utc>, job: Job) {
+  queue_job(&mut SCHEDULER.queue.lock().unwrap(), date, job);
+  SCHEDULER.wake_up.notify_one();
+}
+
+/// Run a job again later, for the ones that keep their data until they succeed.
+pub fn retry(job: Job) {
+  schedule(
+    Utc::now() + chrono::Duration::minutes(RETRY_DELAY_MINUTES),
+    job,
+  );
+}
+
+fn queue_job(queue: &mut JobQueue, date: DateTime<Utc>, job: Job) {
+  queue.retain(|Reverse((_, queued))| *queued != job);
+  queue.push(Reverse((date, job)));
+}
+
+/// Queue the jobs stored in the database.
+fn load_pending_jobs() {
+  // Before taking the lock, business days read the closure days and reminders their offsets
+  schedule(absences::next_summary_date(), Job::AbsenceSummary);
+  schedule(project_manager::next_summary_date(), Job::DeadlineSummary);
+  project_manager::schedule_all_reminders();
+  let db_instance = INSTANCE.read().unwrap();
+  for event in &db_instance.events {
+    schedule(events::job_date(event), Job::Event(event.id));
+  }
+  for announcement in &db_instance.announcements {
+    schedule(
+      announcement.trigger_date.and_utc(),
+      Job::Announcement(announcement.id),
+    );
+  }
+  for blocked in db_instance.filter_storage_type(StorageDataType::Blocked) {
+    if let Some(until) = blocked.date {
+      schedule(until.into(), Job::Unblock(blocked.id));
+    }
+  }
+  for project in &db_instance.projects {
+    if let Some(deletion) = project_manager::deletion_date(project) {
+      schedule(deletion, Job::ProjectDeletion(project.id));
+    }
+  }
+  schedule(Utc::now(), Job::Retention);
+  schedule(Utc::now(), Job::MemberReconciliation);
+  info!(
+    "Scheduler: loaded {} pending jobs",
+    SCHEDULER.queue.lock().unwrap().len()
+  );
+}
+
+/// Remove the jobs that are due from the queue, and return the date of the next one.
+fn pop_due_jobs(now: DateTime<Utc>) -> (Vec<Job>, Option<DateTime<Utc>>) {
+  let mut queue = SCHEDULER.queue.lock().unwrap();
+  let mut due = Vec::new();
+  while let Some(Reverse((date, job))) = queue.peek() {
+    if *date > now {
+      return (due, Some(*date));
+    }
+    due.push(*job);
+    queue.pop();
+  }
+  (due, None)
+}
+
+async fn run_job(http: Arc<Http>, job: Job) {
+  let result = match job {
+    Job::Event(event_id) => events::fire_event(&http, event_id).await,
+    Job::Announcement(announcement_id) => {
+      announcements::fire_announcement(&http, announcement_id).await
+    }
+    Job::Unblock(storage_id) => {
+      let mut db_instance = INSTANCE.write().unwrap();
+      let is_blocked = db_instance
+        .storage
+        .iter()
+        .any(|stored| stored.id == storage_id);
+      if is_blocked {
+        db_instance.storage_delete(vec![storage_id]);
+      }
+      Ok(())
+    }
+    Job::Retention => retention::retention_job(&http).await,
+    Job::AbsenceSummary => absences::summary_job(&http).await,
+    Job::ProjectDeletion(project_id) => project_manager::deletion_job(&http, project_id).await,
+    Job::DeadlineReminder(project_id, days_before) => {
+      project_manager::reminder_job(&http, project_id, days_before).await
+    }
+    Job::DeadlineSummary => project_manager::summary_job(&http).await,
+    Job::MemberReconciliation => project_manager::reconciliation_job(&http).await,
+  };
+  if let Err(error) = result {
+    error!("Scheduler: {:?} failed: {}", job, error);
+  }
+}
+
+/// Sleep until the next job is due and run it, forever.
+pub async fn run(http: Arc<Http>) {
+  info!("running scheduler");
+  load_pending_jobs();
+  loop {
+    let (due, next) = pop_due_jobs(Utc::now());
+    // Each job has its own task so a slow one doesn't delay the others
+    for job in due {
+      tokio::spawn(run_job(http.clone(), job));
+    }
+    match next {
+      Some(next) => {
+        let delay = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+          _ = tokio::time::sleep(delay) => (),
+          _ = SCHEDULER.wake_up.notified() => (),
+        }
+      }
+      None => SCHEDULER.wake_up.notified().await,
+    }
+  }
+}
+
+#[test]
+fn test_reschedule_same_job() {
+  let mut queue = JobQueue::new();
+  let now = Utc::now();
+  let later = now + chrono::Duration::hours(1);
+  queue_job(&mut queue, now, Job::Event(1));
+  queue_job(&mut queue, now, Job::Event(2));
+  queue_job(&mut queue, later, Job::Event(1));
+  assert_eq!(queue.len(), 2);
+  assert!(queue
+    .iter()
+    .any(|Reverse(queued)| *queued == (later, Job::Event(1))));
+}
@@ -1,21 +1,28 @@
 use std::time::SystemTime;
 
-use crate::features::funny;
+use crate::features::{events, funny, message_history, project_manager};
 use crate::{constants, features::minecraft};
 use chrono::{Datelike, Utc};
+use log::error;
 use procedural_macros::command;
 use serenity::{
   client::Context,
   model::{
     application::{
-      command::CommandOptionType,
+      command::{CommandOptionType, CommandType},
       interaction::{Interaction, InteractionResponseType},
     },
     id::GuildId,
   },
 };
 
-use super::commands::{CallBackParams, CallbackReturn};
+use super::{
+  commands::{CallBackParams, CallbackReturn},
+  parse,
+};
+
+/// Name of the message context-menu entry showing the edits of a message.
+const EDIT_HISTORY_COMMAND: &str = "Edit history";
 
 #[command]
 pub async fn set(params: CallBackParams) -> CallbackReturn {
@@ -48,6 +55,42 @@ pub async fn set(params: CallBackParams) -> CallbackReturn {
             .name("playing-mc")
             .description("Get the list of users connected to minecraft")
         })
+        .create_application_command(|command| {
+          command
+            .name("projects")
+            .description("List the projects")
+            .create_option(|o| {
+              o.name("client")
+                .description("Part of the client name")
+                .kind(CommandOptionType::String)
+                .set_autocomplete(true)
+            })
+            .create_option(|o| {
+              o.name("lead")
+                .description("Part of the lead name")
+                .kind(CommandOptionType::String)
+                .set_autocomplete(true)
+            })
+            .create_option(|o| {
+              o.name("codex")
+                .description("Part of the codex")
+                .kind(CommandOptionType::String)
+            })
+            .create_option(|o| {
+              o.name("status")
+                .description("Projects with this status only")
+                .kind(CommandOptionType::String)
+                .add_string_choice("active", "active")
+                .add_string_choice("on hold", "on_hold")
+                .add_string_choice("archived", "archived")
+                .add_string_choice("deleted", "deleted")
+            })
+        })
+        .create_application_command(|command| {
+          command
+            .name(EDIT_HISTORY_COMMAND)
+            .kind(CommandType::Message)
+        })
     })
     .await
     .unwrap();
@@ -56,6 +99,25 @@ pub async fn set(params: CallBackParams) -> CallbackReturn {
 }
 
 pub async fn handle_event(interaction: Interaction, ctx: Context) {
+  if let Interaction::MessageComponent(component) = &interaction {
+    if let Err(error) = events::handle_reminder_button(&ctx, component).await {
+      error!("Unable to handle the reminder button: {}", error);
+    }
+    if let Err(error) = project_manager::handle_projects_button(&ctx, component).await {
+      error!("Unable to handle the projects button: {}", error);
+    }
+    if let Err(error) = project_manager::handle_directory_select(&ctx, component).await {
+      error!("Unable to handle the project directory: {}", error);
+    }
+    if let Err(error) = project_manager::handle_access_button(&ctx, component).await {
+      error!("Unable to handle the access request: {}", error);
+    }
+  }
+  if let Interaction::Autocomplete(autocomplete) = &interaction {
+    if let Err(error) = project_manager::projects_autocomplete(&ctx, autocomplete).await {
+      error!("Unable to autocomplete the projects filters: {}", error);
+    }
+  }
   if let Interaction::ApplicationCommand(command) = interaction {
     match &*command.data.name {
       "mom" => {
@@ -117,6 +179,41 @@ pub async fn handle_event(interaction: Interaction, ctx: Context) {
           .await
           .unwrap()
       }
+      "projects" => {
+        if let Err(error) = project_manager::projects_slash_command(&ctx, &command).await {
+          error!("Unable to list the projects: {}", error);
+        }
+      }
+      EDIT_HISTORY_COMMAND => {
+        let message_id = command.data.target_id.unwrap().to_message_id();
+        let report = message_history::message_history_report(
+          &ctx,
+          command.guild_id.map(|guild_id| guild_id.0),
+          message_id.0,
+          command.user.id,
+        )
+        .await;
+        let mut messages = parse::split_reply(&report).into_iter();
+        let first = messages.next().unwrap_or_default();
+        command
+          .create_interaction_response(&ctx.http, |res| {
+            res
+              .kind(InteractionResponseType::ChannelMessageWithSource)
+              .interaction_response_data(|resdata| resdata.content(first).ephemeral(true))
+          })
+          .await
+          .unwrap();
+        for message in messages {
+          if let Err(error) = command
+            .create_followup_message(&ctx.http, |followup| {
+              followup.content(message).ephemeral(true)
+            })
+            .await
+          {
+            error!("Unable to send the edit history: {}", error);
+          }
+        }
+      }
       _ => {}
     }
   }
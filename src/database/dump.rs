@@ -0,0 +1,405 @@
+//! JSON export and import of the bot state, the messages history is not part of it.
+//!
+//! Also export and erase everything stored about a single user.
+use super::models::*;
+use super::{Instance, StorageDataType};
+use diesel::prelude::*;
+use std::{collections::HashSet, error::Error};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Dump {
+  pub users: Vec<User>,
+  pub projects: Vec<Project>,
+  pub invites: Vec<Invite>,
+  pub storage: Vec<Storage>,
+  pub events: Vec<Event>,
+  #[serde(default)]
+  pub project_members: Vec<ProjectMember>,
+  #[serde(default)]
+  pub project_channels: Vec<ProjectChannel>,
+  #[serde(default)]
+  pub project_access_requests: Vec<ProjectAccessRequest>,
+  #[serde(default)]
+  pub announcements: Vec<Announcement>,
+  /// Read from the database, the files are not kept in the [Instance]
+  #[serde(default)]
+  pub announcement_files: Vec<AnnouncementFile>,
+  /// Read from the database, the history is not kept in the [Instance]
+  #[serde(default)]
+  pub project_history: Vec<ProjectChange>,
+  #[serde(default)]
+  pub absences: Vec<Absence>,
+  #[serde(default)]
+  pub retention_policies: Vec<RetentionPolicy>,
+}
+
+/// Tables replaced by [Instance::restore], their serial sequence is reset after the import.
+const DUMP_TABLES: [&str; 13] = [
+  "users",
+  "projects",
+  "invites",
+  "storage",
+  "events",
+  "project_members",
+  "project_channels",
+  "project_access_requests",
+  "project_history",
+  "announcements",
+  "announcement_files",
+  "absences",
+  "retention_policies",
+];
+
+impl Instance {
+  pub fn dump(&self) -> Result<Dump, Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+    Ok(Dump {
+      users: self.users.clone(),
+      projects: self.projects.clone(),
+      invites: self.invites.clone(),
+      storage: self.storage.clone(),
+      events: self.events.clone(),
+      project_members: self.project_members.clone(),
+      project_channels: self.project_channels.clone(),
+      project_access_requests: self.project_access_requests.clone(),
+      announcements: self.announcements.clone(),
+      announcement_files: announcement_files::table
+        .order(announcement_files::id)
+        .load(connection)?,
+      project_history: project_history::table
+        .order(project_history::id)
+        .load(connection)?,
+      absences: self.absences.clone(),
+      retention_policies: self.retention_policies.clone(),
+    })
+  }
+
+  /// Replace the content of the dumped tables with `dump` and reload them.
+  pub fn restore(&mut self, dump: Dump) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+
+    connection.transaction::<_, diesel::result::Error, _>(|conn| {
+      diesel::delete(users::table).execute(conn)?;
+      diesel::insert_into(users::table)
+        .values(&dump.users)
+        .execute(conn)?;
+      diesel::delete(projects::table).execute(conn)?;
+      diesel::insert_into(projects::table)
+        .values(&dump.projects)
+        .execute(conn)?;
+      diesel::delete(invites::table).execute(conn)?;
+      diesel::insert_into(invites::table)
+        .values(&dump.invites)
+        .execute(conn)?;
+      diesel::delete(storage::table).execute(conn)?;
+      diesel::insert_into(storage::table)
+        .values(&dump.storage)
+        .execute(conn)?;
+      diesel::delete(events::table).execute(conn)?;
+      diesel::insert_into(events::table)
+        .values(&dump.events)
+        .execute(conn)?;
+      // Deleted along with the projects
+      diesel::insert_into(project_members::table)
+        .values(&dump.project_members)
+        .execute(conn)?;
+      diesel::insert_into(project_channels::table)
+        .values(&dump.project_channels)
+        .execute(conn)?;
+      diesel::insert_into(project_access_requests::table)
+        .values(&dump.project_access_requests)
+        .execute(conn)?;
+      diesel::insert_into(project_history::table)
+        .values(&dump.project_history)
+        .execute(conn)?;
+      diesel::delete(announcements::table).execute(conn)?;
+      diesel::insert_into(announcements::table)
+        .values(&dump.announcements)
+        .execute(conn)?;
+      diesel::insert_into(announcement_files::table)
+        .values(&dump.announcement_files)
+        .execute(conn)?;
+      diesel::delete(absences::table).execute(conn)?;
+      diesel::insert_into(absences::table)
+        .values(&dump.absences)
+        .execute(conn)?;
+      diesel::delete(retention_policies::table).execute(conn)?;
+      diesel::insert_into(retention_policies::table)
+        .values(&dump.retention_policies)
+        .execute(conn)?;
+
+      for table in DUMP_TABLES {
+        diesel::sql_query(format!(
+          "SELECT setval(pg_get_serial_sequence('{table}', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM {table}"
+        ))
+        .execute(conn)?;
+      }
+      Ok(())
+    })?;
+
+    self.user_load();
+    self.projects_load();
+    self.project_members_load();
+    self.project_channels_load();
+    self.project_access_requests_load();
+    self.invites_load();
+    self.storage_load();
+    self.events_load();
+    self.announcements_load();
+    self.absences_load();
+    self.retention_policies_load();
+    Ok(())
+  }
+}
+
+/// Everything stored about a discord user.
+#[derive(Serialize, Debug)]
+pub struct UserData {
+  pub user: Option<User>,
+  pub messages: Vec<Message>,
+  pub messages_edits: Vec<MessageEdit>,
+  pub events: Vec<Event>,
+  pub storage: Vec<Storage>,
+  pub absences: Vec<Absence>,
+  pub project_members: Vec<ProjectMember>,
+  pub project_access_requests: Vec<ProjectAccessRequest>,
+  pub announcements: Vec<Announcement>,
+  /// Changes made to the fiches of the projects
+  pub project_history: Vec<ProjectChange>,
+}
+
+/// Number of rows removed by [Instance::user_forget].
+#[derive(Debug, Default)]
+pub struct ForgetReport {
+  pub messages: usize,
+  pub messages_edits: usize,
+  pub events: usize,
+  pub storage: usize,
+  pub absences: usize,
+  pub project_members: usize,
+  pub project_access_requests: usize,
+  pub announcements: usize,
+  pub project_history: usize,
+  pub user: usize,
+}
+
+/// Storage entries about a user that are kept when forgetting it.
+fn is_kept_storage(stored: &Storage) -> bool {
+  stored.datatype == i64::from(StorageDataType::Forgotten)
+}
+
+impl Instance {
+  pub fn user_data(&self, discord_id: i64) -> Result<UserData, Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+    Ok(UserData {
+      user: self
+        .users
+        .iter()
+        .find(|user| user.discordid == discord_id)
+        .cloned(),
+      messages: self
+        .messages
+        .iter()
+        .filter(|message| message.author == discord_id)
+        .cloned()
+        .collect(),
+      messages_edits: self
+        .messages_edits
+        .iter()
+        .filter(|edit| edit.author == discord_id)
+        .cloned()
+        .collect(),
+      events: self
+        .events
+        .iter()
+        .filter(|event| event.author == discord_id)
+        .cloned()
+        .collect(),
+      storage: self
+        .storage
+        .iter()
+        .filter(|stored| stored.dataid == Some(discord_id) && !is_kept_storage(stored))
+        .cloned()
+        .collect(),
+      absences: self
+        .absences
+        .iter()
+        .filter(|absence| absence.user_id == discord_id)
+        .cloned()
+        .collect(),
+      project_members: self
+        .project_members
+        .iter()
+        .filter(|member| member.user_id == discord_id)
+        .cloned()
+        .collect(),
+      project_access_requests: self
+        .project_access_requests
+        .iter()
+        .filter(|request| request.user_id == discord_id)
+        .cloned()
+        .collect(),
+      announcements: self
+        .announcements
+        .iter()
+        .filter(|announcement| announcement.author == discord_id)
+        .cloned()
+        .collect(),
+      // Read from the database, the history is not kept in the [Instance]
+      project_history: project_history::table
+        .filter(project_history::author.eq(discord_id))
+        .order(project_history::id)
+        .load(connection)?,
+    })
+  }
+
+  /// Delete the messages, edits, events, storage entries, absences, project memberships, access
+  /// requests, announcements, changes of the project fiches and user row of a discord user.
+  ///
+  /// Edits of other users made on the deleted messages are removed as well.
+  pub fn user_forget(
+    &mut self,
+    discord_id: i64,
+  ) -> Result<ForgetReport, Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+    let kept_storage = i64::from(StorageDataType::Forgotten);
+
+    let report = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+      let authored_messages = messages::table
+        .filter(messages::author.eq(discord_id))
+        .select(messages::id);
+      Ok(ForgetReport {
+        messages_edits: diesel::delete(
+          messages_edits::table.filter(
+            messages_edits::author
+              .eq(discord_id)
+              .or(messages_edits::parrent_message_id.eq_any(authored_messages)),
+          ),
+        )
+        .execute(conn)?,
+        messages: diesel::delete(messages::table.filter(messages::author.eq(discord_id)))
+          .execute(conn)?,
+        events: diesel::delete(events::table.filter(events::author.eq(discord_id)))
+          .execute(conn)?,
+        storage: diesel::delete(
+          storage::table
+            .filter(storage::dataid.eq(discord_id))
+            .filter(storage::datatype.ne(kept_storage)),
+        )
+        .execute(conn)?,
+        absences: diesel::delete(absences::table.filter(absences::user_id.eq(discord_id)))
+          .execute(conn)?,
+        project_members: diesel::delete(
+          project_members::table.filter(project_members::user_id.eq(discord_id)),
+        )
+        .execute(conn)?,
+        project_access_requests: diesel::delete(
+          project_access_requests::table.filter(project_access_requests::user_id.eq(discord_id)),
+        )
+        .execute(conn)?,
+        // Their files are deleted along with them
+        announcements: diesel::delete(
+          announcements::table.filter(announcements::author.eq(discord_id)),
+        )
+        .execute(conn)?,
+        project_history: diesel::delete(
+          project_history::table.filter(project_history::author.eq(discord_id)),
+        )
+        .execute(conn)?,
+        user: diesel::delete(users::table.filter(users::discordid.eq(discord_id))).execute(conn)?,
+      })
+    })?;
+
+    let forgotten_messages: HashSet<i64> = self
+      .messages
+      .iter()
+      .filter(|message| message.author == discord_id)
+      .map(|message| message.id)
+      .collect();
+    self.messages_edits.retain(|edit| {
+      edit.author != discord_id && !forgotten_messages.contains(&edit.parrent_message_id)
+    });
+    self.messages.retain(|message| message.author != discord_id);
+    self.events.retain(|event| event.author != discord_id);
+    self
+      .storage
+      .retain(|stored| stored.dataid != Some(discord_id) || is_kept_storage(stored));
+    self
+      .absences
+      .retain(|absence| absence.user_id != discord_id);
+    self
+      .project_members
+      .retain(|member| member.user_id != discord_id);
+    self
+      .project_access_requests
+      .retain(|request| request.user_id != discord_id);
+    self
+      .announcements
+      .retain(|announcement| announcement.author != discord_id);
+    self.users.retain(|user| user.discordid != discord_id);
+    Ok(report)
+  }
+}
+
+#[test]
+fn test_dump_restore() {
+  use chrono::NaiveDate;
+  use std::time::{Duration, UNIX_EPOCH};
+
+  let mut instance = Instance::new();
+  let original = instance.dump().unwrap();
+  let date = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+  let sample = Dump {
+    users: vec![User {
+      id: 1,
+      discordid: 10,
+      role: String::from("User"),
+      timezone: None,
+    }],
+    projects: vec![Project {
+      id: 1,
+      message_id: 20,
+      channel_id: 30,
+      codex: String::from("codex"),
+      client: String::from("client"),
+      lead: String::from("lead"),
+      deadline: String::from("2026-11-01"),
+      description: String::from("description"),
+      contexte: String::from("contexte"),
+      created_at: UNIX_EPOCH + Duration::from_secs(1_790_000_000),
+      pinned_message_id: None,
+      status: ProjectStatus::Active.to_string(),
+      deleted_at: None,
+      deadline_date: NaiveDate::from_ymd_opt(2026, 11, 1),
+      access: ProjectAccess::Open.to_string(),
+    }],
+    project_members: vec![ProjectMember {
+      id: 1,
+      project_id: 1,
+      user_id: 10,
+      source: MemberSource::Reaction.to_string(),
+      joined_at: date.and_hms_opt(8, 30, 0).unwrap(),
+    }],
+    absences: vec![Absence {
+      id: 1,
+      user_id: 10,
+      start_date: date,
+      end_date: date,
+      reason: Some(String::from("holidays")),
+    }],
+    retention_policies: vec![RetentionPolicy {
+      id: 1,
+      guild_id: 40,
+      channel_id: Some(30),
+      keep_days: Some(30),
+      store: true,
+    }],
+    ..Default::default()
+  };
+  let expected = serde_json::to_value(&sample).unwrap();
+
+  instance.restore(sample).unwrap();
+  let restored = serde_json::to_value(instance.dump().unwrap()).unwrap();
+  instance.restore(original).unwrap();
+  assert_eq!(restored, expected);
+}
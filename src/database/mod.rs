@@ -1,4 +1,5 @@
 mod connection;
+mod dump;
 mod models;
 mod queries;
 mod schema;
@@ -6,6 +7,7 @@ mod schema;
 use self::connection::{establish_connection, PgPool, PgPooledConnection};
 use std::sync::RwLock;
 
+pub use self::dump::{Dump, ForgetReport, UserData};
 pub use self::models::{Message, User};
 pub use queries::*;
 
@@ -24,6 +26,12 @@ impl Instance {
       invites: Vec::new(),
       storage: Vec::new(),
       events: Vec::new(),
+      retention_policies: Vec::new(),
+      announcements: Vec::new(),
+      absences: Vec::new(),
+      project_members: Vec::new(),
+      project_channels: Vec::new(),
+      project_access_requests: Vec::new(),
     };
     instance.user_load();
     instance.message_load();
@@ -32,6 +40,12 @@ impl Instance {
     instance.invites_load();
     instance.storage_load();
     instance.events_load();
+    instance.retention_policies_load();
+    instance.announcements_load();
+    instance.absences_load();
+    instance.project_members_load();
+    instance.project_channels_load();
+    instance.project_access_requests_load();
     instance
   }
 
@@ -49,13 +63,37 @@ pub struct Instance {
   pub storage: Vec<Storage>,
   pub messages_edits: Vec<MessageEdit>,
   pub events: Vec<Event>,
+  pub retention_policies: Vec<RetentionPolicy>,
+  pub announcements: Vec<Announcement>,
+  pub absences: Vec<Absence>,
+  pub project_members: Vec<ProjectMember>,
+  pub project_channels: Vec<ProjectChannel>,
+  pub project_access_requests: Vec<ProjectAccessRequest>,
 }
 
 #[derive(Debug, Clone)]
 pub enum StorageDataType {
   Mom,
+  /// Replaced by [StorageDataType::ProjectDirectory], kept so the following ids don't change.
   ProjectBottomMessage,
   Blocked,
+  /// Audit trail of the users erased with `forget`, `dataid` being the erased user.
+  Forgotten,
+  /// Company closure day, `date` being the day and `data` its label.
+  ClosureDay,
+  /// Secret of the calendar links of a user, `dataid` being the user.
+  CalendarToken,
+  /// Channel of the morning summary of the absences, `dataid` being the channel.
+  AbsenceChannel,
+  /// Days before a project deadline when it is reminded, `data` being the days separated by `,`.
+  DeadlineReminders,
+  /// Message of the project directory, `dataid` being the message.
+  ProjectDirectory,
+  /// Template of the projects, `data` being the template as JSON.
+  ProjectTemplate,
+  /// Category created when the previous ones of its family were full, `dataid` being the category
+  /// and `data` its family.
+  OverflowCategory,
 }
 
 impl From<StorageDataType> for i64 {
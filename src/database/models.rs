@@ -1,12 +1,14 @@
 #![allow(clippy::extra_unused_lifetimes)]
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use strum_macros::{Display, EnumString};
 
-#[derive(Queryable, Debug)]
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
 pub struct User {
   pub id: i32,
   pub discordid: i64,
   pub role: String,
+  /// IANA name of the user time zone, `None` for the default one
+  pub timezone: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -16,7 +18,7 @@ pub struct NewUser<'a> {
   pub role: &'a str,
 }
 
-#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[derive(Insertable, Queryable, Debug, Serialize, Deserialize, Clone)]
 #[diesel(table_name = messages)]
 pub struct Message {
   pub id: i64,
@@ -55,8 +57,18 @@ pub enum Role {
   Admin,
 }
 
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum ProjectStatus {
+  Active,
+  OnHold,
+  /// The channel is in the archive category
+  Archived,
+  Deleted,
+}
+
 #[allow(dead_code)]
-#[derive(Queryable, Debug)]
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
   pub id: i32,
   pub message_id: i64,
@@ -69,6 +81,39 @@ pub struct Project {
   pub contexte: String,
   pub created_at: std::time::SystemTime,
   pub pinned_message_id: Option<i64>,
+  /// A [ProjectStatus]
+  #[serde(default)]
+  pub status: String,
+  /// When the project was deleted, it is removed for good after a grace period
+  #[serde(default)]
+  pub deleted_at: Option<std::time::SystemTime>,
+  /// `deadline` parsed when it is written, `None` when it isn't a date
+  #[serde(default)]
+  pub deadline_date: Option<NaiveDate>,
+  /// A [ProjectAccess]
+  #[serde(default)]
+  pub access: String,
+}
+
+impl Project {
+  pub fn status(&self) -> ProjectStatus {
+    self.status.parse().unwrap_or(ProjectStatus::Active)
+  }
+
+  pub fn access(&self) -> ProjectAccess {
+    self.access.parse().unwrap_or(ProjectAccess::Open)
+  }
+}
+
+/// Who can join a project by reacting to its announcement or from the directory.
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum ProjectAccess {
+  Open,
+  /// The lead approves each request
+  Approval,
+  /// Only added with `add-user`
+  Closed,
 }
 
 #[derive(Insertable, Debug)]
@@ -83,9 +128,146 @@ pub struct NewProject<'a> {
   pub description: Option<&'a str>,
   pub contexte: Option<&'a str>,
   pub pinned_message_id: Option<i64>,
+  pub deadline_date: Option<NaiveDate>,
+}
+
+/// Fields of a project that can be changed after its creation, `None` fields are left untouched.
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = projects)]
+pub struct ProjectChangeset<'a> {
+  pub codex: Option<&'a str>,
+  pub client: Option<&'a str>,
+  pub lead: Option<&'a str>,
+  pub deadline: Option<&'a str>,
+  pub description: Option<&'a str>,
+  pub contexte: Option<&'a str>,
+  pub status: Option<&'a str>,
+  pub deleted_at: Option<Option<std::time::SystemTime>>,
+  pub deadline_date: Option<Option<NaiveDate>>,
+  pub access: Option<&'a str>,
+}
+
+/// A field of a project fiche changed by `project edit`, not kept in the [super::Instance].
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize)]
+#[diesel(table_name = project_history)]
+pub struct ProjectChange {
+  pub id: i32,
+  pub project_id: i32,
+  pub author: i64,
+  pub field: String,
+  pub old_value: String,
+  pub new_value: String,
+  pub date: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = project_history)]
+pub struct NewProjectChange<'a> {
+  pub project_id: i32,
+  pub author: i64,
+  pub field: &'a str,
+  pub old_value: &'a str,
+  pub new_value: &'a str,
+}
+
+/// A channel created for a project by its template, besides its main channel.
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = project_channels)]
+pub struct ProjectChannel {
+  pub id: i32,
+  pub project_id: i32,
+  pub channel_id: i64,
+  /// `text`, `voice` or `forum`
+  pub kind: String,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = project_channels)]
+pub struct NewProjectChannel<'a> {
+  pub project_id: i32,
+  pub channel_id: i64,
+  pub kind: &'a str,
+}
+
+/// How a user joined a project.
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum MemberSource {
+  /// Created the project
+  Creator,
+  /// Added with `add-user`
+  Command,
+  /// Reacted to the announcement of the project
+  Reaction,
+  /// Picked the project in the directory
+  Directory,
+  /// Listed in the template of the project
+  Template,
+  /// Given access to the channel outside of the bot, found by the reconciliation
+  Discord,
+  /// Already in the channel when the members of the project were first recorded
+  Imported,
+}
+
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = project_members)]
+pub struct ProjectMember {
+  pub id: i32,
+  pub project_id: i32,
+  pub user_id: i64,
+  /// A [MemberSource]
+  pub source: String,
+  pub joined_at: NaiveDateTime,
 }
 
-#[derive(Queryable, Debug, Clone)]
+#[derive(Insertable, Debug)]
+#[diesel(table_name = project_members)]
+pub struct NewProjectMember<'a> {
+  pub project_id: i32,
+  pub user_id: i64,
+  pub source: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum AccessRequestStatus {
+  Pending,
+  Approved,
+  Denied,
+  /// Withdrawn by the user before the decision
+  Cancelled,
+}
+
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = project_access_requests)]
+pub struct ProjectAccessRequest {
+  pub id: i32,
+  pub project_id: i32,
+  pub user_id: i64,
+  /// The [MemberSource] recorded once approved
+  pub source: String,
+  /// An [AccessRequestStatus]
+  pub status: String,
+  pub requested_at: NaiveDateTime,
+  pub decided_by: Option<i64>,
+  pub decided_at: Option<NaiveDateTime>,
+}
+
+impl ProjectAccessRequest {
+  pub fn status(&self) -> AccessRequestStatus {
+    self.status.parse().unwrap_or(AccessRequestStatus::Pending)
+  }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = project_access_requests)]
+pub struct NewProjectAccessRequest<'a> {
+  pub project_id: i32,
+  pub user_id: i64,
+  pub source: &'a str,
+}
+
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
 pub struct Invite {
   pub id: i32,
   pub code: String,
@@ -104,7 +286,8 @@ pub struct NewInvite {
 }
 
 #[allow(dead_code)]
-#[derive(Queryable, Debug, Clone)]
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = storage)]
 pub struct Storage {
   pub id: i32,
   pub datatype: i64,
@@ -122,13 +305,25 @@ pub struct NewStorage<'a> {
   pub date: Option<std::time::SystemTime>,
 }
 
-#[derive(Queryable, Debug, Clone)]
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
 pub struct Event {
   pub id: i32,
   pub author: i64,
   pub content: String,
   pub channel: i64,
   pub trigger_date: NaiveDateTime,
+  /// Rule of a repeating event, see [crate::features::events::recurrence]
+  pub recurrence: Option<String>,
+  /// Discord mention of the user or role pinged, empty to ping nobody
+  #[serde(default)]
+  pub mention: String,
+  /// Sent by direct message to the mentioned user
+  #[serde(default)]
+  pub dm: bool,
+  pub message_link: Option<String>,
+  /// One-shot event already sent, kept until acknowledged to be snoozed
+  #[serde(default)]
+  pub fired: bool,
 }
 
 #[derive(Insertable, Debug)]
@@ -138,6 +333,130 @@ pub struct NewEvent<'a> {
   pub content: &'a str,
   pub channel: i64,
   pub trigger_date: NaiveDateTime,
+  pub recurrence: Option<&'a str>,
+  pub mention: &'a str,
+  pub dm: bool,
+  pub message_link: Option<&'a str>,
+}
+
+/// Fields of an event that can be changed, `None` fields are left untouched.
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = events)]
+pub struct EventChangeset<'a> {
+  pub content: Option<&'a str>,
+  pub trigger_date: Option<NaiveDateTime>,
+  pub recurrence: Option<Option<&'a str>>,
+  pub fired: Option<bool>,
 }
 
 pub use super::schema::*;
+
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = retention_policies)]
+pub struct RetentionPolicy {
+  pub id: i32,
+  pub guild_id: i64,
+  /// `None` for the default policy of the guild
+  pub channel_id: Option<i64>,
+  /// `None` to keep the messages forever
+  pub keep_days: Option<i32>,
+  /// Messages aren't saved at all when `false`
+  pub store: bool,
+}
+
+impl RetentionPolicy {
+  /// Policy of the channel, or of the parent channel of a thread, or the default one of the guild.
+  pub fn applying_to(
+    policies: &[RetentionPolicy],
+    guild: Option<i64>,
+    channel: i64,
+    parent: Option<i64>,
+  ) -> Option<&RetentionPolicy> {
+    let channel_policy = |channel: i64| {
+      policies
+        .iter()
+        .find(|policy| policy.channel_id == Some(channel))
+    };
+    channel_policy(channel)
+      .or_else(|| parent.and_then(channel_policy))
+      .or_else(|| {
+        policies
+          .iter()
+          .find(|policy| policy.channel_id.is_none() && Some(policy.guild_id) == guild)
+      })
+  }
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = retention_policies, treat_none_as_null = true)]
+pub struct NewRetentionPolicy {
+  pub guild_id: i64,
+  pub channel_id: Option<i64>,
+  pub keep_days: Option<i32>,
+  pub store: bool,
+}
+
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = announcements)]
+pub struct Announcement {
+  pub id: i32,
+  pub author: i64,
+  pub channel: i64,
+  pub content: String,
+  pub title: Option<String>,
+  /// Sent as an embed instead of a plain message
+  pub embed: bool,
+  pub trigger_date: NaiveDateTime,
+  /// Rule of a repeating announcement, see [crate::features::events::recurrence]
+  pub recurrence: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = announcements)]
+pub struct NewAnnouncement<'a> {
+  pub author: i64,
+  pub channel: i64,
+  pub content: &'a str,
+  pub title: Option<&'a str>,
+  pub embed: bool,
+  pub trigger_date: NaiveDateTime,
+  pub recurrence: Option<&'a str>,
+}
+
+/// Files sent with an announcement, they are not kept in the [super::Instance].
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize)]
+#[diesel(table_name = announcement_files)]
+pub struct AnnouncementFile {
+  pub id: i32,
+  pub announcement_id: i32,
+  pub filename: String,
+  pub data: Vec<u8>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = announcement_files)]
+pub struct NewAnnouncementFile {
+  pub announcement_id: i32,
+  pub filename: String,
+  pub data: Vec<u8>,
+}
+
+/// Days out of office of a user, `start_date` and `end_date` included.
+#[derive(Queryable, Insertable, Debug, Serialize, Deserialize, Clone)]
+#[diesel(table_name = absences)]
+pub struct Absence {
+  pub id: i32,
+  pub user_id: i64,
+  pub start_date: NaiveDate,
+  pub end_date: NaiveDate,
+  pub reason: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = absences)]
+pub struct NewAbsence<'a> {
+  pub user_id: i64,
+  pub start_date: NaiveDate,
+  pub end_date: NaiveDate,
+  pub reason: Option<&'a str>,
+}
@@ -1,8 +1,26 @@
 pub use super::models::*;
 use super::{Instance, StorageDataType};
 use crate::core::parse::DiscordIds;
-use diesel::prelude::*;
-use std::error::Error;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{
+  dsl::{not, sql},
+  pg::Pg,
+  prelude::*,
+  sql_types::{Bool, Text},
+};
+use std::{collections::HashSet, error::Error, time::SystemTime};
+
+/// Filters of a full text search over the stored messages.
+pub struct MessageSearch<'a> {
+  pub terms: &'a str,
+  pub author: Option<i64>,
+  /// Channels the search is restricted to, usually the ones readable by the user searching.
+  pub channels: Vec<i64>,
+  pub before: Option<SystemTime>,
+  pub after: Option<SystemTime>,
+  /// Messages of and commands sent to the bot are left out of the results.
+  pub bot_id: i64,
+}
 
 impl Instance {
   db_load! {user_load, User, users}
@@ -43,6 +61,22 @@ impl Instance {
     format!("Updated {} to {}", user.discordid, user.role)
   }
 
+  pub fn user_timezone_update(
+    &mut self,
+    discord_id: u64,
+    new_timezone: Option<&str>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use super::schema::users::dsl::*;
+
+    let conn = &mut self.get_connection();
+    let user = self.user_search_mut(discord_id).ok_or("User not found")?;
+    diesel::update(users.find(user.id))
+      .set(timezone.eq(new_timezone))
+      .execute(conn)?;
+    user.timezone = new_timezone.map(String::from);
+    Ok(())
+  }
+
   pub fn user_search(&self, discordid: u64) -> Option<&User> {
     self
       .users
@@ -56,6 +90,21 @@ impl Instance {
   db_add! { message_add, Message, Message, messages }
   db_add! { message_edit_add, NewMessageEdit, MessageEdit, messages_edits }
 
+  /// Original message and its edits sorted from the oldest.
+  pub fn message_history(&self, message_id: i64) -> Option<(&Message, Vec<&MessageEdit>)> {
+    let message = self
+      .messages
+      .iter()
+      .find(|message| message.id == message_id)?;
+    let mut edits: Vec<&MessageEdit> = self
+      .messages_edits
+      .iter()
+      .filter(|edit| edit.parrent_message_id == message_id)
+      .collect();
+    edits.sort_by_key(|edit| (edit.date, edit.id));
+    Some((message, edits))
+  }
+
   #[allow(dead_code)]
   pub fn mesage_delete(&mut self, messages_id: Vec<i64>) -> Vec<Message> {
     use super::schema::messages::dsl::*;
@@ -76,6 +125,166 @@ impl Instance {
     previous_bottom_list
   }
 
+  fn message_search_query<'a>(search: &'a MessageSearch) -> messages::BoxedQuery<'a, Pg> {
+    use super::schema::messages::dsl::*;
+
+    let mut query = messages
+      .into_boxed()
+      .filter(
+        sql::<Bool>("to_tsvector('simple', content) @@ websearch_to_tsquery('simple', ")
+          .bind::<Text, _>(search.terms)
+          .sql(")"),
+      )
+      .filter(channel.eq_any(&search.channels))
+      .filter(author.ne(search.bot_id))
+      .filter(not(content.like(format!("<@{}>%", search.bot_id))))
+      .filter(not(content.like(format!("<@!{}>%", search.bot_id))));
+    if let Some(search_author) = search.author {
+      query = query.filter(author.eq(search_author));
+    }
+    if let Some(before) = search.before {
+      query = query.filter(date.lt(before));
+    }
+    if let Some(after) = search.after {
+      query = query.filter(date.gt(after));
+    }
+    query
+  }
+
+  /// Full text search over the messages, returns the total of matches and the requested page of the most recent ones.
+  pub fn message_search(
+    &self,
+    search: &MessageSearch,
+    offset: i64,
+    limit: i64,
+  ) -> Result<(i64, Vec<Message>), Box<dyn Error + Send + Sync>> {
+    use super::schema::messages::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let total = Self::message_search_query(search)
+      .count()
+      .get_result(connection)?;
+    let results = Self::message_search_query(search)
+      .order(date.desc())
+      .offset(offset)
+      .limit(limit)
+      .load(connection)?;
+    Ok((total, results))
+  }
+
+  db_load! {retention_policies_load, RetentionPolicy, retention_policies}
+
+  /// Policy of the channel, of its parent when it is a thread, or the default one of its guild.
+  pub fn retention_policy(
+    &self,
+    guild: Option<i64>,
+    channel: i64,
+    parent: Option<i64>,
+  ) -> Option<&RetentionPolicy> {
+    RetentionPolicy::applying_to(&self.retention_policies, guild, channel, parent)
+  }
+
+  /// Create or replace the policy of a channel, or of the guild when `channel_id` is `None`.
+  pub fn retention_policy_set(
+    &mut self,
+    policy: NewRetentionPolicy,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use super::schema::retention_policies::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let existing = self
+      .retention_policies
+      .iter()
+      .position(|p| p.guild_id == policy.guild_id && p.channel_id == policy.channel_id);
+    match existing {
+      Some(index) => {
+        let updated: RetentionPolicy =
+          diesel::update(retention_policies.find(self.retention_policies[index].id))
+            .set(&policy)
+            .get_result(connection)?;
+        self.retention_policies[index] = updated;
+      }
+      None => {
+        let inserted: RetentionPolicy = diesel::insert_into(retention_policies)
+          .values(&policy)
+          .get_result(connection)?;
+        self.retention_policies.push(inserted);
+      }
+    }
+    Ok(())
+  }
+
+  /// Remove a policy so the channel falls back to its guild default, and the guild to keeping everything.
+  pub fn retention_policy_delete(
+    &mut self,
+    guild: i64,
+    channel: Option<i64>,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    use super::schema::retention_policies::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let Some(index) = self
+      .retention_policies
+      .iter()
+      .position(|p| p.guild_id == guild && p.channel_id == channel)
+    else {
+      return Ok(false);
+    };
+    diesel::delete(retention_policies.find(self.retention_policies[index].id))
+      .execute(connection)?;
+    self.retention_policies.remove(index);
+    Ok(true)
+  }
+
+  /// Delete the messages of `channels` older than `before` along with their edits, returns the number of messages deleted.
+  pub fn messages_purge(
+    &mut self,
+    channels: &[i64],
+    before: SystemTime,
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+
+    let deleted = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+      let expired = messages::table
+        .filter(messages::channel.eq_any(channels))
+        .filter(messages::date.lt(before))
+        .select(messages::id);
+      diesel::delete(
+        messages_edits::table.filter(
+          messages_edits::parrent_message_id.eq_any(expired).or(
+            messages_edits::channel
+              .eq_any(channels)
+              .and(messages_edits::date.lt(before)),
+          ),
+        ),
+      )
+      .execute(conn)?;
+      diesel::delete(
+        messages::table
+          .filter(messages::channel.eq_any(channels))
+          .filter(messages::date.lt(before)),
+      )
+      .execute(conn)
+    })?;
+
+    let is_expired = |channel: i64, date: Option<SystemTime>| {
+      channels.contains(&channel) && date.is_some_and(|date| date < before)
+    };
+    let expired: HashSet<i64> = self
+      .messages
+      .iter()
+      .filter(|message| is_expired(message.channel, message.date))
+      .map(|message| message.id)
+      .collect();
+    self.messages_edits.retain(|edit| {
+      !expired.contains(&edit.parrent_message_id) && !is_expired(edit.channel, edit.date)
+    });
+    self
+      .messages
+      .retain(|message| !expired.contains(&message.id));
+    Ok(deleted)
+  }
+
   db_add! {project_add, NewProject, Project, projects}
 
   db_load! {projects_load, Project, projects}
@@ -110,11 +319,143 @@ impl Instance {
     if let Some((index, project)) = self.projects_search(p_channel_id as i64, DiscordIds::Channel) {
       diesel::delete(projects.filter(id.eq(project.id))).execute(connection)?;
       let project = self.projects.remove(index);
+      // Removed along with the project by the database
+      self
+        .project_members
+        .retain(|member| member.project_id != project.id);
+      self
+        .project_channels
+        .retain(|channel| channel.project_id != project.id);
+      self
+        .project_access_requests
+        .retain(|request| request.project_id != project.id);
       return Ok((":ok:", Some(project)));
     }
     Ok(("Channel wasn't found", None))
   }
 
+  pub fn project_update(
+    &mut self,
+    project_id: i32,
+    changes: ProjectChangeset,
+  ) -> Result<&Project, Box<dyn Error + Send + Sync>> {
+    use super::schema::projects::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let updated: Project = diesel::update(projects.find(project_id))
+      .set(&changes)
+      .get_result(connection)?;
+    let project = self
+      .projects
+      .iter_mut()
+      .find(|project| project.id == project_id)
+      .ok_or("Project updated in database but missing from the instance")?;
+    *project = updated;
+    Ok(project)
+  }
+
+  pub fn project_history_add(
+    &mut self,
+    changes: &[NewProjectChange],
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+    Ok(
+      diesel::insert_into(project_history::table)
+        .values(changes)
+        .execute(connection)?,
+    )
+  }
+
+  /// Changes of the fiche of a project, the oldest first.
+  pub fn project_history(
+    &self,
+    project: i32,
+  ) -> Result<Vec<ProjectChange>, Box<dyn Error + Send + Sync>> {
+    use super::schema::project_history::dsl::*;
+
+    let connection = &mut self.get_connection();
+    Ok(
+      project_history
+        .filter(project_id.eq(project))
+        .order(id)
+        .load(connection)?,
+    )
+  }
+
+  db_load! {project_members_load, ProjectMember, project_members}
+
+  db_load! {project_channels_load, ProjectChannel, project_channels}
+  db_add! {project_channel_add, NewProjectChannel, ProjectChannel, project_channels}
+
+  /// Record a member of a project, unless it is already recorded.
+  pub fn project_member_add(
+    &mut self,
+    new: NewProjectMember,
+  ) -> Result<Option<&ProjectMember>, Box<dyn Error + Send + Sync>> {
+    let exists = self
+      .project_members
+      .iter()
+      .any(|member| member.project_id == new.project_id && member.user_id == new.user_id);
+    if exists {
+      return Ok(None);
+    }
+    let member: ProjectMember = diesel::insert_into(project_members::table)
+      .values(&new)
+      .get_result(&mut self.get_connection())?;
+    self.project_members.push(member);
+    Ok(self.project_members.last())
+  }
+
+  pub fn project_member_remove(
+    &mut self,
+    p_project_id: i32,
+    p_user_id: i64,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    use super::schema::project_members::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let deleted = diesel::delete(
+      project_members
+        .filter(project_id.eq(p_project_id))
+        .filter(user_id.eq(p_user_id)),
+    )
+    .execute(connection)?;
+    self
+      .project_members
+      .retain(|member| member.project_id != p_project_id || member.user_id != p_user_id);
+    Ok(deleted > 0)
+  }
+
+  db_load! {project_access_requests_load, ProjectAccessRequest, project_access_requests}
+  db_add! {project_access_request_add, NewProjectAccessRequest, ProjectAccessRequest, project_access_requests}
+
+  /// Record the decision taken on a pending access request, `decider` being `None` when the user
+  /// withdrew it.
+  pub fn project_access_request_decide(
+    &mut self,
+    request_id: i32,
+    decision: AccessRequestStatus,
+    decider: Option<i64>,
+  ) -> Result<&ProjectAccessRequest, Box<dyn Error + Send + Sync>> {
+    use super::schema::project_access_requests::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let updated: ProjectAccessRequest = diesel::update(project_access_requests.find(request_id))
+      .set((
+        status.eq(decision.to_string()),
+        decided_by.eq(decider),
+        decided_at.eq(diesel::dsl::now),
+      ))
+      .get_result(connection)?;
+    let request = self
+      .project_access_requests
+      .iter_mut()
+      .find(|request| request.id == request_id)
+      .ok_or("Access request updated in database but missing from the instance")?;
+    *request = updated;
+    Ok(request)
+  }
+
   db_load! {invites_load, Invite, invites}
 
   pub fn invite_search(&mut self, code: &str) -> Option<&mut Invite> {
@@ -213,6 +554,26 @@ impl Instance {
   db_load! {events_load, Event, events}
   db_add! {event_add, NewEvent, Event, events}
 
+  pub fn event_update(
+    &mut self,
+    event_id: i32,
+    changes: EventChangeset,
+  ) -> Result<&Event, Box<dyn Error + Send + Sync>> {
+    use super::schema::events::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let updated: Event = diesel::update(events.find(event_id))
+      .set(&changes)
+      .get_result(connection)?;
+    let event = self
+      .events
+      .iter_mut()
+      .find(|event| event.id == event_id)
+      .ok_or("Event updated in database but missing from the instance")?;
+    *event = updated;
+    Ok(event)
+  }
+
   pub fn event_delete(&mut self, event_id: i32) {
     use super::schema::events::dsl::*;
 
@@ -227,4 +588,90 @@ impl Instance {
       self.events.remove(pos);
     }
   }
+
+  db_load! {announcements_load, Announcement, announcements}
+  db_add! {announcement_add, NewAnnouncement, Announcement, announcements}
+
+  pub fn announcement_files_add(
+    &mut self,
+    files: &[NewAnnouncementFile],
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let connection = &mut self.get_connection();
+    Ok(
+      diesel::insert_into(announcement_files::table)
+        .values(files)
+        .execute(connection)?,
+    )
+  }
+
+  pub fn announcement_files(
+    &self,
+    announcement: i32,
+  ) -> Result<Vec<AnnouncementFile>, Box<dyn Error + Send + Sync>> {
+    use super::schema::announcement_files::dsl::*;
+
+    let connection = &mut self.get_connection();
+    Ok(
+      announcement_files
+        .filter(announcement_id.eq(announcement))
+        .order(id)
+        .load(connection)?,
+    )
+  }
+
+  pub fn announcement_reschedule(
+    &mut self,
+    announcement_id: i32,
+    next: NaiveDateTime,
+  ) -> Result<&Announcement, Box<dyn Error + Send + Sync>> {
+    use super::schema::announcements::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let updated: Announcement = diesel::update(announcements.find(announcement_id))
+      .set(trigger_date.eq(next))
+      .get_result(connection)?;
+    let announcement = self
+      .announcements
+      .iter_mut()
+      .find(|announcement| announcement.id == announcement_id)
+      .ok_or("Announcement updated in database but missing from the instance")?;
+    *announcement = updated;
+    Ok(announcement)
+  }
+
+  /// Delete an announcement and its files.
+  pub fn announcement_delete(
+    &mut self,
+    announcement_id: i32,
+  ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    use super::schema::announcements::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let deleted = diesel::delete(announcements.find(announcement_id)).execute(connection)?;
+    self
+      .announcements
+      .retain(|announcement| announcement.id != announcement_id);
+    Ok(deleted > 0)
+  }
+
+  db_load! {absences_load, Absence, absences}
+  db_add! {absence_add, NewAbsence, Absence, absences}
+
+  pub fn absence_delete(&mut self, absence_id: i32) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    use super::schema::absences::dsl::*;
+
+    let connection = &mut self.get_connection();
+    let deleted = diesel::delete(absences.find(absence_id)).execute(connection)?;
+    self.absences.retain(|absence| absence.id != absence_id);
+    Ok(deleted > 0)
+  }
+
+  /// Absences that include `date`.
+  pub fn absences_on(&self, date: NaiveDate) -> Vec<&Absence> {
+    self
+      .absences
+      .iter()
+      .filter(|absence| absence.start_date <= date && date <= absence.end_date)
+      .collect()
+  }
 }
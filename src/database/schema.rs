@@ -1,5 +1,15 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    absences (id) {
+        id -> Int4,
+        user_id -> Int8,
+        start_date -> Date,
+        end_date -> Date,
+        reason -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     airtable (id) {
         id -> Int4,
@@ -9,6 +19,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    announcement_files (id) {
+        id -> Int4,
+        announcement_id -> Int4,
+        filename -> Varchar,
+        data -> Bytea,
+    }
+}
+
+diesel::table! {
+    announcements (id) {
+        id -> Int4,
+        author -> Int8,
+        channel -> Int8,
+        content -> Varchar,
+        title -> Nullable<Varchar>,
+        embed -> Bool,
+        trigger_date -> Timestamp,
+        recurrence -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     events (id) {
         id -> Int4,
@@ -16,6 +48,11 @@ diesel::table! {
         content -> Varchar,
         channel -> Int8,
         trigger_date -> Timestamp,
+        recurrence -> Nullable<Varchar>,
+        mention -> Varchar,
+        dm -> Bool,
+        message_link -> Nullable<Varchar>,
+        fired -> Bool,
     }
 }
 
@@ -50,6 +87,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    project_access_requests (id) {
+        id -> Int4,
+        project_id -> Int4,
+        user_id -> Int8,
+        source -> Varchar,
+        status -> Varchar,
+        requested_at -> Timestamp,
+        decided_by -> Nullable<Int8>,
+        decided_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    project_channels (id) {
+        id -> Int4,
+        project_id -> Int4,
+        channel_id -> Int8,
+        kind -> Varchar,
+    }
+}
+
+diesel::table! {
+    project_history (id) {
+        id -> Int4,
+        project_id -> Int4,
+        author -> Int8,
+        field -> Varchar,
+        old_value -> Varchar,
+        new_value -> Varchar,
+        date -> Timestamp,
+    }
+}
+
+diesel::table! {
+    project_members (id) {
+        id -> Int4,
+        project_id -> Int4,
+        user_id -> Int8,
+        source -> Varchar,
+        joined_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     projects (id) {
         id -> Int4,
@@ -63,6 +144,20 @@ diesel::table! {
         contexte -> Varchar,
         created_at -> Timestamp,
         pinned_message_id -> Nullable<Int8>,
+        status -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+        deadline_date -> Nullable<Date>,
+        access -> Varchar,
+    }
+}
+
+diesel::table! {
+    retention_policies (id) {
+        id -> Int4,
+        guild_id -> Int8,
+        channel_id -> Nullable<Int8>,
+        keep_days -> Nullable<Int4>,
+        store -> Bool,
     }
 }
 
@@ -81,18 +176,32 @@ diesel::table! {
         id -> Int4,
         discordid -> Int8,
         role -> Varchar,
+        timezone -> Nullable<Varchar>,
     }
 }
 
+diesel::joinable!(announcement_files -> announcements (announcement_id));
 diesel::joinable!(messages_edits -> messages (parrent_message_id));
+diesel::joinable!(project_access_requests -> projects (project_id));
+diesel::joinable!(project_channels -> projects (project_id));
+diesel::joinable!(project_history -> projects (project_id));
+diesel::joinable!(project_members -> projects (project_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    absences,
     airtable,
+    announcement_files,
+    announcements,
     events,
     invites,
     messages,
     messages_edits,
+    project_access_requests,
+    project_channels,
+    project_history,
+    project_members,
     projects,
+    retention_policies,
     storage,
     users,
 );
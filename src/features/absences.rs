@@ -0,0 +1,358 @@
+//! Out of office tracking: the absences recorded by the users, a morning summary in a configured
+//! channel and an automatic reply when someone mentions an absent user.
+//!
+//! The absences are kept by day in the `absences` table so they can be compared with the CRA.
+use std::{collections::HashSet, error::Error, fmt::Write, sync::Mutex};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+    parse::{self, DiscordIds},
+    permissions,
+    scheduler::{self, Job},
+  },
+  database::{Absence, NewAbsence, NewStorage, Role, StorageDataType, INSTANCE},
+  features::events::recurrence::Recurrence,
+};
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use log::error;
+use procedural_macros::command;
+use serenity::{
+  http::Http,
+  model::{channel::Message, id::ChannelId},
+  prelude::*,
+};
+
+const OOO_USAGE: &str = "Usage: @BOT ooo <FROM> <TO> [<REASON>] | list | cancel <id>";
+const WHO_IS_OUT_USAGE: &str = "Usage: @BOT who-is-out [<DATE>] | channel <#channel>|off";
+/// Local time of the morning summary, posted on business days.
+const SUMMARY_HOUR: u32 = 9;
+
+lazy_static! {
+  /// Absences already replied to in a channel, as (absence id, channel id).
+  static ref REPLIED: Mutex<HashSet<(i32, u64)>> = Mutex::new(HashSet::new());
+}
+
+/// A day written by a user: a date, or a day relative to now like `lundi`.
+fn parse_absence_day(input: &str, now: DateTime<Tz>) -> Result<NaiveDate, String> {
+  match date_parse::parse_day(input, now.date_naive()) {
+    Some(day) => Ok(day),
+    None => date_parse::parse_date_time(input, now).map(|date| date.date_naive()),
+  }
+}
+
+fn format_day(day: NaiveDate) -> String {
+  day.format("%A %d/%m/%Y").to_string()
+}
+
+fn describe_absence(absence: &Absence) -> String {
+  let mut description = if absence.start_date == absence.end_date {
+    format!("on {}", format_day(absence.start_date))
+  } else {
+    format!(
+      "from {} to {}",
+      format_day(absence.start_date),
+      format_day(absence.end_date)
+    )
+  };
+  if let Some(reason) = &absence.reason {
+    write!(description, " ({})", reason).expect("unable to append string");
+  }
+  description
+}
+
+/// One line per user absent on `date`.
+fn absent_on(date: NaiveDate) -> Vec<String> {
+  let db_instance = INSTANCE.read().unwrap();
+  let mut absences = db_instance.absences_on(date);
+  absences.sort_by_key(|absence| absence.end_date);
+  absences
+    .into_iter()
+    .map(|absence| {
+      let mut line = format!(
+        "<@{}> until {}",
+        absence.user_id,
+        format_day(absence.end_date)
+      );
+      if let Some(reason) = &absence.reason {
+        write!(line, ": {}", reason).expect("unable to append string");
+      }
+      line
+    })
+    .collect()
+}
+
+fn add_absence(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let [_, from, to, reason @ ..] = params.args else {
+    return Ok(String::from(OOO_USAGE));
+  };
+  let author = params.message.author.id.0;
+  let now = date_parse::user_now(author);
+  let (start_date, end_date) = match (parse_absence_day(from, now), parse_absence_day(to, now)) {
+    (Ok(start_date), Ok(end_date)) => (start_date, end_date),
+    (Err(error), _) | (_, Err(error)) => return Ok(error),
+  };
+  if end_date < start_date {
+    return Ok(String::from("The end of the absence is before its start"));
+  }
+
+  let mut db_instance = INSTANCE.write().unwrap();
+  let overlap = db_instance.absences.iter().find(|absence| {
+    absence.user_id == author as i64
+      && absence.start_date <= end_date
+      && start_date <= absence.end_date
+  });
+  if let Some(overlap) = overlap {
+    return Ok(format!(
+      "You are already out {}, cancel it with `ooo cancel {}`",
+      describe_absence(overlap),
+      overlap.id
+    ));
+  }
+  let absence = db_instance.absence_add(NewAbsence {
+    user_id: author as i64,
+    start_date,
+    end_date,
+    reason: reason.first().map(String::as_str),
+  });
+  Ok(format!(
+    "Absence `{}` recorded {}",
+    absence.id,
+    describe_absence(absence)
+  ))
+}
+
+#[command]
+pub async fn ooo(params: CallBackParams) -> CallbackReturn {
+  let author = params.message.author.id;
+  match (params.args[1].as_str(), params.args.get(2)) {
+    ("list", None) => {
+      let today = date_parse::user_now(author.0).date_naive();
+      let db_instance = INSTANCE.read().unwrap();
+      let mut absences: Vec<&Absence> = db_instance
+        .absences
+        .iter()
+        .filter(|absence| absence.user_id == author.0 as i64 && absence.end_date >= today)
+        .collect();
+      if absences.is_empty() {
+        return Ok(Some(String::from("No absence planned")));
+      }
+      absences.sort_by_key(|absence| absence.start_date);
+      let mut reply = String::from("Your absences:\n");
+      for absence in absences {
+        writeln!(reply, "`{}` {}", absence.id, describe_absence(absence))
+          .expect("unable to append string");
+      }
+      Ok(Some(reply))
+    }
+    ("cancel", Some(id)) => {
+      let Ok(id) = id.parse::<i32>() else {
+        return Ok(Some(format!("Invalid absence id: {}", id)));
+      };
+      let is_admin = permissions::user_role(author) >= Role::Admin;
+      let mut db_instance = INSTANCE.write().unwrap();
+      let found = db_instance
+        .absences
+        .iter()
+        .any(|absence| absence.id == id && (absence.user_id == author.0 as i64 || is_admin));
+      if !found {
+        return Ok(Some(format!("Absence {} not found", id)));
+      }
+      db_instance.absence_delete(id)?;
+      Ok(Some(String::from(":ok:")))
+    }
+    _ => Ok(Some(add_absence(&params)?)),
+  }
+}
+
+#[command]
+pub async fn who_is_out(params: CallBackParams) -> CallbackReturn {
+  let author = params.message.author.id;
+  match (params.args.get(1).map(String::as_str), params.args.get(2)) {
+    (Some("channel"), Some(channel)) => {
+      if permissions::user_role(author) < Role::Admin {
+        return Ok(Some(String::from(
+          "Only admins can set the channel of the summary",
+        )));
+      }
+      let channel = match channel.as_str() {
+        "off" => None,
+        channel => match parse::discord_str_to_id(channel, Some(DiscordIds::Channel)) {
+          Ok((channel, _)) => Some(channel),
+          Err(error) => return Ok(Some(error)),
+        },
+      };
+      let mut db_instance = INSTANCE.write().unwrap();
+      let existing = db_instance
+        .filter_storage_type(StorageDataType::AbsenceChannel)
+        .into_iter()
+        .map(|stored| stored.id)
+        .collect();
+      db_instance.storage_delete(existing);
+      if let Some(channel) = channel {
+        db_instance.storage_add(NewStorage {
+          datatype: StorageDataType::AbsenceChannel.into(),
+          dataid: Some(channel as i64),
+          data: "",
+          date: None,
+        });
+      }
+      Ok(Some(String::from(":ok:")))
+    }
+    (Some("channel"), None) => Ok(Some(String::from(WHO_IS_OUT_USAGE))),
+    (date, None) => {
+      let now = date_parse::user_now(author.0);
+      let date = match date.map(|date| parse_absence_day(date, now)) {
+        None => now.date_naive(),
+        Some(Ok(date)) => date,
+        Some(Err(error)) => return Ok(Some(error)),
+      };
+      let absent = absent_on(date);
+      if absent.is_empty() {
+        return Ok(Some(format!("Nobody is out on {}", format_day(date))));
+      }
+      Ok(Some(format!(
+        "Out on {}:\n{}",
+        format_day(date),
+        absent.join("\n")
+      )))
+    }
+    _ => Ok(Some(String::from(WHO_IS_OUT_USAGE))),
+  }
+}
+
+/// Date of the next morning summary, on the next business day.
+pub fn next_summary_date() -> DateTime<Utc> {
+  let summary = Recurrence::BusinessDays {
+    time: NaiveTime::from_hms_opt(SUMMARY_HOUR, 0, 0).unwrap(),
+  };
+  summary
+    .next_after(Utc::now(), date_parse::DEFAULT_TIMEZONE)
+    .expect("there is always a next business day")
+}
+
+/// Post who is out today in the configured channel, then schedule the next summary.
+pub async fn summary_job(http: &Http) -> Result<(), Box<dyn Error + Send + Sync>> {
+  scheduler::schedule(next_summary_date(), Job::AbsenceSummary);
+  let channel = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .find_storage_type(StorageDataType::AbsenceChannel)
+      .and_then(|stored| stored.dataid)
+  };
+  let today = Utc::now()
+    .with_timezone(&date_parse::DEFAULT_TIMEZONE)
+    .date_naive();
+  let absent = absent_on(today);
+  let Some(channel) = channel.filter(|_| !absent.is_empty()) else {
+    return Ok(());
+  };
+  ChannelId(channel as u64)
+    .send_message(http, |message| {
+      message
+        .content(format!("Out of office today:\n{}", absent.join("\n")))
+        // Absent users don't need a notification
+        .allowed_mentions(|mentions| mentions.empty_parse())
+    })
+    .await?;
+  Ok(())
+}
+
+/// Keep the absences not replied to yet in the channel and remember them, forgetting the ones that
+/// are over.
+fn first_replies(
+  replied: &mut HashSet<(i32, u64)>,
+  current: &[i32],
+  channel: u64,
+  absences: Vec<(i32, String)>,
+) -> Vec<String> {
+  replied.retain(|(absence, _)| current.contains(absence));
+  absences
+    .into_iter()
+    .filter(|(absence, _)| replied.insert((*absence, channel)))
+    .map(|(_, reply)| reply)
+    .collect()
+}
+
+/// Tell the author of a message when the users mentioned are out of office, once per absence and
+/// channel.
+pub async fn out_of_office_reply(ctx: &Context, message: &Message) {
+  if message.author.bot || message.mentions.is_empty() {
+    return;
+  }
+  let today = Utc::now()
+    .with_timezone(&date_parse::DEFAULT_TIMEZONE)
+    .date_naive();
+  let (current, absent): (Vec<i32>, Vec<(i32, String)>) = {
+    let db_instance = INSTANCE.read().unwrap();
+    let absences = db_instance.absences_on(today);
+    let absent = message
+      .mentions
+      .iter()
+      .filter(|user| user.id != message.author.id)
+      .filter_map(|user| {
+        let absence = absences
+          .iter()
+          .find(|absence| absence.user_id == user.id.0 as i64)?;
+        Some((
+          absence.id,
+          format!(
+            "{} is out of office {}",
+            user.name,
+            describe_absence(absence)
+          ),
+        ))
+      })
+      .collect();
+    (absences.iter().map(|absence| absence.id).collect(), absent)
+  };
+  let replies = first_replies(
+    &mut REPLIED.lock().unwrap(),
+    &current,
+    message.channel_id.0,
+    absent,
+  );
+  if replies.is_empty() {
+    return;
+  }
+  if let Err(e) = message.reply(&ctx.http, replies.join("\n")).await {
+    error!("Unable to send the out of office reply: {}", e);
+  }
+}
+
+#[test]
+fn test_parse_absence_day() {
+  let now = chrono_tz::Europe::Paris
+    .with_ymd_and_hms(2026, 10, 19, 10, 0, 0)
+    .unwrap();
+  let day = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+  assert_eq!(parse_absence_day("demain", now), Ok(day(10, 20)));
+  assert_eq!(parse_absence_day("23/10", now), Ok(day(10, 23)));
+  assert_eq!(parse_absence_day("friday", now), Ok(day(10, 23)));
+  assert!(parse_absence_day("someday", now).is_err());
+}
+
+#[test]
+fn test_first_replies() {
+  let mut replied = HashSet::new();
+  let reply = |absence: i32| (absence, absence.to_string());
+  assert_eq!(
+    first_replies(&mut replied, &[1, 2], 10, vec![reply(1)]),
+    ["1"]
+  );
+  assert!(first_replies(&mut replied, &[1, 2], 10, vec![reply(1)]).is_empty());
+  // Another channel, then another absence in the same channel
+  assert_eq!(
+    first_replies(&mut replied, &[1, 2], 11, vec![reply(1)]),
+    ["1"]
+  );
+  assert_eq!(
+    first_replies(&mut replied, &[1, 2], 10, vec![reply(1), reply(2)]),
+    ["2"]
+  );
+  // Absence 1 is over, a new absence with the same id later is replied to again
+  assert!(first_replies(&mut replied, &[2], 10, Vec::new()).is_empty());
+  assert_eq!(first_replies(&mut replied, &[1], 10, vec![reply(1)]), ["1"]);
+}
@@ -0,0 +1,278 @@
+//! Messages scheduled by admins and project leads to be posted in a channel.
+use std::{borrow::Cow, error::Error, fmt::Write};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+    parse::{self, DiscordIds},
+    permissions,
+    scheduler::{self, Job},
+  },
+  database::{Announcement, NewAnnouncement, NewAnnouncementFile, Role, INSTANCE},
+  features::{
+    events::{self, recurrence::Recurrence},
+    project_manager,
+  },
+};
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use log::error;
+use procedural_macros::command;
+use serenity::{
+  http::Http,
+  model::{channel::AttachmentType, id::ChannelId},
+};
+
+const ANNOUNCE_USAGE: &str = "Usage: @BOT announce <#channel> <WHEN> <CONTENT> [embed] [title=<TITLE>] | list | cancel <id> | preview <id>";
+
+/// Post the announcement, with its files, in a channel.
+async fn send_announcement(
+  http: &Http,
+  channel: ChannelId,
+  announcement: &Announcement,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let files = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance.announcement_files(announcement.id)?
+  };
+  channel
+    .send_message(http, |message| {
+      if announcement.embed {
+        message.embed(|embed| {
+          if let Some(title) = &announcement.title {
+            embed.title(title);
+          }
+          embed.description(&announcement.content)
+        });
+      } else if let Some(title) = &announcement.title {
+        message.content(format!("**{}**\n{}", title, announcement.content));
+      } else {
+        message.content(&announcement.content);
+      }
+      message.add_files(files.into_iter().map(|file| AttachmentType::Bytes {
+        data: Cow::from(file.data),
+        filename: file.filename,
+      }))
+    })
+    .await?;
+  Ok(())
+}
+
+/// Post an announcement that is due, then reschedule it if it repeats or delete it.
+///
+/// An announcement that doesn't repeat is kept and retried when it couldn't be sent.
+pub async fn fire_announcement(
+  http: &Http,
+  announcement_id: i32,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let announcement = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .announcements
+      .iter()
+      .find(|announcement| announcement.id == announcement_id)
+      .cloned()
+  };
+  let Some(announcement) =
+    announcement.filter(|announcement| announcement.trigger_date <= Utc::now().naive_utc())
+  else {
+    return Ok(());
+  };
+
+  let sent = send_announcement(http, ChannelId(announcement.channel as u64), &announcement).await;
+
+  let next = match announcement
+    .recurrence
+    .as_deref()
+    .map(str::parse::<Recurrence>)
+  {
+    Some(Ok(recurrence)) => recurrence.next_from(
+      announcement.trigger_date.and_utc(),
+      Utc::now(),
+      date_parse::user_timezone(announcement.author as u64),
+    ),
+    Some(Err(e)) => {
+      error!(
+        "invalid recurrence of announcement {}: {}",
+        announcement_id, e
+      );
+      None
+    }
+    None => None,
+  };
+  let mut db_instance = INSTANCE.write().unwrap();
+  match next {
+    Some(next) => {
+      db_instance.announcement_reschedule(announcement_id, next.naive_utc())?;
+      scheduler::schedule(next, Job::Announcement(announcement_id));
+    }
+    None if sent.is_err() => scheduler::retry(Job::Announcement(announcement_id)),
+    None => {
+      db_instance.announcement_delete(announcement_id)?;
+    }
+  }
+  sent?;
+  Ok(())
+}
+
+fn describe_announcement(announcement: &Announcement, timezone: Tz) -> String {
+  let next = timezone.from_utc_datetime(&announcement.trigger_date);
+  let mut preview: String = announcement.content.chars().take(80).collect();
+  if preview.len() < announcement.content.len() {
+    preview.push('…');
+  }
+  format!(
+    "`{}` next on {} in <#{}> ({}) by <@{}>: {}",
+    announcement.id,
+    date_parse::format_date_time(&next),
+    announcement.channel,
+    announcement.recurrence.as_deref().unwrap_or("once"),
+    announcement.author,
+    preview.replace('\n', " ")
+  )
+}
+
+/// Find an announcement the user is allowed to manage: its own, or any for admins.
+fn find_announcement(id: &str, author: u64, is_admin: bool) -> Result<Announcement, String> {
+  let id: i32 = id
+    .parse()
+    .map_err(|_| format!("Invalid announcement id: {}", id))?;
+  let db_instance = INSTANCE.read().unwrap();
+  db_instance
+    .announcements
+    .iter()
+    .find(|announcement| announcement.id == id)
+    .filter(|announcement| announcement.author == author as i64 || is_admin)
+    .cloned()
+    .ok_or_else(|| format!("Announcement {} not found", id))
+}
+
+async fn schedule_announcement(
+  params: &CallBackParams<'_>,
+  is_admin: bool,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let [_, channel, when, content, options @ ..] = params.args else {
+    return Ok(String::from(ANNOUNCE_USAGE));
+  };
+  let channel = match parse::discord_str_to_id(channel, Some(DiscordIds::Channel)) {
+    Ok((channel, _)) => ChannelId(channel),
+    Err(error) => return Ok(error),
+  };
+  if !is_admin && !project_manager::is_project_lead(channel, &params.message.author) {
+    return Ok(String::from(
+      "Only admins and the lead of the project can schedule announcements in this channel",
+    ));
+  }
+  let author = params.message.author.id.0;
+  let timezone = date_parse::user_timezone(author);
+  let (trigger_date, recurrence) = match events::parse_when(when, timezone) {
+    Ok(when) => when,
+    Err(error) => return Ok(error),
+  };
+  let mut embed = false;
+  let mut title = None;
+  for option in options {
+    match option.split_once('=') {
+      None if option == "embed" => embed = true,
+      Some(("title", value)) => title = Some(value),
+      _ => return Ok(format!("Unknown option: {}\n{}", option, ANNOUNCE_USAGE)),
+    }
+  }
+
+  // Discord links of the attachments expire, the files are kept in the database
+  let mut files = Vec::new();
+  for attachment in &params.message.attachments {
+    files.push((attachment.filename.clone(), attachment.download().await?));
+  }
+
+  let recurrence = recurrence.map(|recurrence| recurrence.to_string());
+  let mut db_instance = INSTANCE.write().unwrap();
+  let announcement_id = db_instance
+    .announcement_add(NewAnnouncement {
+      author: author as i64,
+      channel: channel.0 as i64,
+      content,
+      title,
+      embed,
+      trigger_date: trigger_date.naive_utc(),
+      recurrence: recurrence.as_deref(),
+    })
+    .id;
+  let files: Vec<NewAnnouncementFile> = files
+    .into_iter()
+    .map(|(filename, data)| NewAnnouncementFile {
+      announcement_id,
+      filename,
+      data,
+    })
+    .collect();
+  db_instance.announcement_files_add(&files)?;
+  scheduler::schedule(
+    trigger_date.with_timezone(&Utc),
+    Job::Announcement(announcement_id),
+  );
+  Ok(format!(
+    "Announcement `{}` scheduled for {}",
+    announcement_id,
+    date_parse::format_date_time(&trigger_date)
+  ))
+}
+
+#[command]
+pub async fn announce(params: CallBackParams) -> CallbackReturn {
+  let author = params.message.author.id;
+  let is_admin = permissions::user_role(author) >= Role::Admin;
+  match (params.args[1].as_str(), params.args.get(2)) {
+    ("list", None) => {
+      let timezone = date_parse::user_timezone(author.0);
+      let db_instance = INSTANCE.read().unwrap();
+      let mut announcements: Vec<&Announcement> = db_instance
+        .announcements
+        .iter()
+        .filter(|announcement| announcement.author == author.0 as i64 || is_admin)
+        .collect();
+      if announcements.is_empty() {
+        return Ok(Some(String::from("No announcement scheduled")));
+      }
+      announcements.sort_by_key(|announcement| announcement.trigger_date);
+      let mut reply = String::from("Scheduled announcements:\n");
+      for announcement in announcements {
+        writeln!(reply, "{}", describe_announcement(announcement, timezone))
+          .expect("unable to append string");
+      }
+      Ok(Some(reply))
+    }
+    ("cancel", Some(id)) => {
+      let announcement = match find_announcement(id, author.0, is_admin) {
+        Ok(announcement) => announcement,
+        Err(error) => return Ok(Some(error)),
+      };
+      let mut db_instance = INSTANCE.write().unwrap();
+      db_instance.announcement_delete(announcement.id)?;
+      Ok(Some(String::from(":ok:")))
+    }
+    ("preview", Some(id)) => {
+      let announcement = match find_announcement(id, author.0, is_admin) {
+        Ok(announcement) => announcement,
+        Err(error) => return Ok(Some(error)),
+      };
+      params
+        .message
+        .channel_id
+        .say(
+          &params.context.http,
+          describe_announcement(&announcement, date_parse::user_timezone(author.0)),
+        )
+        .await?;
+      send_announcement(
+        &params.context.http,
+        params.message.channel_id,
+        &announcement,
+      )
+      .await?;
+      Ok(None)
+    }
+    _ => Ok(Some(schedule_announcement(&params, is_admin).await?)),
+  }
+}
@@ -1,9 +1,16 @@
 use std::{collections::HashMap, fmt::Write};
 
-use crate::core::{
-  commands::{CallBackParams, CallbackReturn},
-  parse,
-  validation::{self, ValidationCallback},
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    parse,
+    validation::{self, ValidationCallback},
+  },
+  database::{ProjectStatus, INSTANCE},
+  features::{
+    categories::{self, CategoryFamily},
+    project_manager,
+  },
 };
 use chrono::prelude::*;
 use futures::FutureExt;
@@ -37,21 +44,36 @@ pub async fn archive_channels_command(params: CallBackParams) -> CallbackReturn
   Ok(None)
 }
 
-// TODO: This is only working for 1 server as channel is static
-use crate::constants::discordids::ARCHIVE_CATEGORY;
 pub async fn move_channels_to_archive(chanids: Vec<u64>, context: &Context) {
   let cache = &context.cache;
   for chanid in chanids {
-    match cache.guild_channel(ChannelId(chanid)) {
-      Some(mut channel) => {
-        if let Err(why) = channel
-          .edit(&context.http, |chan| {
-            chan.category(ChannelId(ARCHIVE_CATEGORY))
-          })
+    let project = {
+      let db_instance = INSTANCE.read().unwrap();
+      db_instance
+        .projects_search(chanid as i64, parse::DiscordIds::Channel)
+        .map(|(_, project)| project.clone())
+    };
+    // Projects also get their status and fiche updated
+    if let Some(project) = project {
+      if !is_archivable(project.status()) {
+        continue;
+      }
+      let bot = cache.current_user_id();
+      if let Err(why) =
+        project_manager::set_project_status(&context.http, &project, ProjectStatus::Archived, bot)
           .await
+      {
+        error!("Unable to archive project {}:\n{}", project.id, why);
+      }
+      continue;
+    }
+    match cache.guild_channel(ChannelId(chanid)) {
+      Some(channel) => {
+        if let Err(why) =
+          categories::move_to_family(&context.http, channel.id, CategoryFamily::Archives).await
         {
           // TODO: Should tell the user about it
-          error!("Unable to edit channel {}:\n{}", channel.name, why);
+          error!("Unable to archive channel {}:\n{}", channel.name, why);
         }
       }
       None => error!("Channel {} not found", chanid),
@@ -59,6 +81,11 @@ pub async fn move_channels_to_archive(chanids: Vec<u64>, context: &Context) {
   }
 }
 
+/// Deleted projects are waiting for their removal and archived ones are already in place.
+fn is_archivable(status: ProjectStatus) -> bool {
+  matches!(status, ProjectStatus::Active | ProjectStatus::OnHold)
+}
+
 pub fn filter_guild_channel(channels: HashMap<ChannelId, Channel>) -> Vec<GuildChannel> {
   channels
     .into_iter()
@@ -77,11 +104,7 @@ async fn check_channels_activity(
   let channels: Vec<_> = channels
     .iter()
     .filter(|chan| {
-      chan.kind == ChannelType::Text
-        && match chan.parent_id {
-          Some(chan) => chan == category,
-          None => category == 0,
-        }
+      chan.kind == ChannelType::Text && categories::in_category(chan.parent_id, category)
     })
     .collect();
   let mut display = String::new();
@@ -146,3 +169,11 @@ pub async fn guild_chanels_archivage<'fut>(
 
   Some((preview_reply, Box::new(func)))
 }
+
+#[test]
+fn test_is_archivable() {
+  assert!(is_archivable(ProjectStatus::Active));
+  assert!(is_archivable(ProjectStatus::OnHold));
+  assert!(!is_archivable(ProjectStatus::Archived));
+  assert!(!is_archivable(ProjectStatus::Deleted));
+}
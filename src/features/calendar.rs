@@ -1,5 +1,8 @@
-use crate::core::commands::{CallBackParams, CallbackReturn};
-use chrono::{NaiveDate, NaiveDateTime, Utc};
+use crate::core::{
+  commands::{CallBackParams, CallbackReturn},
+  date_parse,
+};
+use chrono::{NaiveDate, NaiveDateTime};
 use log::error;
 use procedural_macros::command;
 use reqwest::Client;
@@ -66,7 +69,7 @@ pub async fn check_calendar(params: CallBackParams) -> CallbackReturn {
       Err(_) => return Ok(Some(String::from("Invalid date format"))),
     }
   } else {
-    Utc::now().naive_local().date()
+    date_parse::user_now(params.message.author.id.0).date_naive()
   };
   let warnings = match client
     .get(format!("{}/warnings/", *CRA_SERVER))
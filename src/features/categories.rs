@@ -0,0 +1,229 @@
+//! Families of categories: a category holds at most 50 channels, so the projects overflow into new
+//! categories ("Projects 2", "Projects 3"...) created when the previous ones are full. Archives go
+//! in the categories of the current year ("Archives 2026", "Archives 2026 2"...). The categories of
+//! a family are used as one by the archivage and the ordering.
+//!
+//! The categories created by the bot are kept in the storage, after the base category.
+use std::{collections::HashMap, error::Error};
+
+use crate::{
+  constants::discordids::{ARCHIVE_CATEGORY, PROJECT_CATEGORY},
+  core::parse,
+  database::{NewStorage, StorageDataType, INSTANCE},
+};
+use chrono::{Datelike, Utc};
+use log::info;
+use serenity::{
+  builder::CreateChannel,
+  http::Http,
+  model::{
+    channel::{ChannelType, GuildChannel},
+    id::ChannelId,
+  },
+};
+use strum_macros::Display;
+
+/// Discord limit of channels in a category.
+const CATEGORY_LIMIT: usize = 50;
+
+lazy_static! {
+  /// Held from the choice of a category until the channel is in it, so two channels don't take the
+  /// last place of a category and a family doesn't overflow twice.
+  static ref OVERFLOW: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum CategoryFamily {
+  Projects,
+  Archives,
+}
+
+impl CategoryFamily {
+  fn base(self) -> ChannelId {
+    match self {
+      CategoryFamily::Projects => ChannelId(PROJECT_CATEGORY),
+      CategoryFamily::Archives => ChannelId(ARCHIVE_CATEGORY),
+    }
+  }
+
+  /// The base category followed by the overflow ones, in their order of creation.
+  pub fn categories(self) -> Vec<ChannelId> {
+    let db_instance = INSTANCE.read().unwrap();
+    let family = self.to_string();
+    let mut overflows: Vec<(i32, ChannelId)> = db_instance
+      .filter_storage_type(StorageDataType::OverflowCategory)
+      .into_iter()
+      .filter(|stored| stored.data == family)
+      .filter_map(|stored| Some((stored.id, ChannelId(stored.dataid? as u64))))
+      .collect();
+    overflows.sort_unstable();
+    let mut categories = vec![self.base()];
+    categories.extend(overflows.into_iter().map(|(_, category)| category));
+    categories
+  }
+
+  pub fn contains(self, category: ChannelId) -> bool {
+    self.categories().contains(&category)
+  }
+
+  /// Family of a category, if it belongs to one.
+  pub fn of(category: ChannelId) -> Option<Self> {
+    [CategoryFamily::Projects, CategoryFamily::Archives]
+      .iter()
+      .copied()
+      .find(|family| family.contains(category))
+  }
+
+  /// Whether new channels can go in the category named `name`: archives only go in the categories
+  /// of the current year.
+  fn accepts(self, name: &str, year: i32) -> bool {
+    match self {
+      CategoryFamily::Projects => true,
+      CategoryFamily::Archives => {
+        let year_name = format!("Archives {}", year);
+        name == year_name
+          || name
+            .strip_prefix(&year_name)
+            .and_then(|index| index.strip_prefix(' '))
+            .is_some_and(|index| index.parse::<u32>().is_ok())
+      }
+    }
+  }
+
+  /// Name of the next overflow category, not yet used by `existing`.
+  fn overflow_name(self, existing: &[&str], year: i32) -> String {
+    let prefix = match self {
+      CategoryFamily::Projects => String::from("Projects"),
+      CategoryFamily::Archives => format!("Archives {}", year),
+    };
+    if self == CategoryFamily::Archives && !existing.contains(&prefix.as_str()) {
+      return prefix;
+    }
+    (2..)
+      .map(|index| format!("{} {}", prefix, index))
+      .find(|name| !existing.contains(&name.as_str()))
+      .expect("there is always an unused name")
+  }
+}
+
+/// Whether a channel is in the category, or in any category of its family. The category `0`
+/// stands for the channels outside of any category.
+pub fn in_category(parent_id: Option<ChannelId>, category: u64) -> bool {
+  let Some(parent_id) = parent_id else {
+    return category == 0;
+  };
+  if parent_id == category {
+    return true;
+  }
+  CategoryFamily::of(ChannelId(category)).is_some_and(|family| family.contains(parent_id))
+}
+
+fn channels_in(channels: &HashMap<ChannelId, GuildChannel>, category: ChannelId) -> usize {
+  channels
+    .values()
+    .filter(|channel| channel.parent_id == Some(category))
+    .count()
+}
+
+/// A category of the family with room for another channel, the overflow category being created
+/// when all of them are full. [OVERFLOW] must be held until the channel is in the category.
+async fn category_with_room(
+  http: &Http,
+  family: CategoryFamily,
+) -> Result<ChannelId, Box<dyn Error + Send + Sync>> {
+  let guild = parse::main_guild_id();
+  let channels = guild.channels(http).await?;
+  let year = Utc::now().year();
+  // Overflow categories deleted by hand are skipped
+  let categories: Vec<&GuildChannel> = family
+    .categories()
+    .iter()
+    .filter_map(|category| channels.get(category))
+    .collect();
+  if let Some(category) = categories.iter().find(|category| {
+    family.accepts(&category.name, year) && channels_in(&channels, category.id) < CATEGORY_LIMIT
+  }) {
+    return Ok(category.id);
+  }
+
+  let existing: Vec<&str> = channels
+    .values()
+    .filter(|channel| channel.kind == ChannelType::Category)
+    .map(|channel| channel.name.as_str())
+    .collect();
+  let name = family.overflow_name(&existing, year);
+  let last = categories.last().ok_or("the base category doesn't exist")?;
+  let category = guild
+    .create_channel(http, |channel| {
+      channel
+        .name(&name)
+        .kind(ChannelType::Category)
+        .position((last.position + 1) as u32)
+        .permissions(last.permission_overwrites.clone())
+    })
+    .await?;
+  info!("Created the category {} for the {}", name, family);
+  let mut db_instance = INSTANCE.write().unwrap();
+  db_instance.storage_add(NewStorage {
+    datatype: StorageDataType::OverflowCategory.into(),
+    dataid: Some(category.id.0 as i64),
+    data: &family.to_string(),
+    date: None,
+  });
+  Ok(category.id)
+}
+
+/// Create a channel in a category of the family with room for it.
+pub async fn create_in_family(
+  http: &Http,
+  family: CategoryFamily,
+  build: impl FnOnce(&mut CreateChannel) -> &mut CreateChannel,
+) -> Result<GuildChannel, Box<dyn Error + Send + Sync>> {
+  let _overflow = OVERFLOW.lock().await;
+  let category = category_with_room(http, family).await?;
+  Ok(
+    parse::main_guild_id()
+      .create_channel(http, |channel| build(channel).category(category))
+      .await?,
+  )
+}
+
+/// Move a channel in a category of the family with room for it.
+pub async fn move_to_family(
+  http: &Http,
+  channel: ChannelId,
+  family: CategoryFamily,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let _overflow = OVERFLOW.lock().await;
+  let category = category_with_room(http, family).await?;
+  channel.edit(http, |edit| edit.category(category)).await?;
+  Ok(())
+}
+
+#[test]
+fn test_accepts() {
+  assert!(CategoryFamily::Projects.accepts("Projects 2", 2026));
+  let archives = CategoryFamily::Archives;
+  assert!(archives.accepts("Archives 2026", 2026));
+  assert!(archives.accepts("Archives 2026 2", 2026));
+  assert!(!archives.accepts("Archives", 2026));
+  assert!(!archives.accepts("Archives 2025", 2026));
+  assert!(!archives.accepts("Archives 2026 old", 2026));
+}
+
+#[test]
+fn test_overflow_name() {
+  let projects = CategoryFamily::Projects;
+  assert_eq!(projects.overflow_name(&["Projects"], 2026), "Projects 2");
+  assert_eq!(
+    projects.overflow_name(&["Projects", "Projects 2"], 2026),
+    "Projects 3"
+  );
+  let archives = CategoryFamily::Archives;
+  assert_eq!(archives.overflow_name(&["Archives"], 2026), "Archives 2026");
+  assert_eq!(
+    archives.overflow_name(&["Archives", "Archives 2026"], 2026),
+    "Archives 2026 2"
+  );
+}
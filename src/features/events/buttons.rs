@@ -0,0 +1,229 @@
+//! Buttons sent with the reminders to acknowledge, snooze or repeat them.
+use std::error::Error;
+
+use super::{job_date, recurrence::Recurrence};
+use crate::{
+  core::{
+    date_parse,
+    scheduler::{self, Job},
+  },
+  database::{Event, EventChangeset, NewEvent, INSTANCE},
+};
+use chrono::{prelude::*, Duration};
+use serenity::{
+  builder::CreateComponents,
+  client::Context,
+  model::application::{
+    component::ButtonStyle,
+    interaction::{message_component::MessageComponentInteraction, InteractionResponseType},
+  },
+};
+
+/// Prefix of the custom id of the buttons, followed by the action and the event id.
+const BUTTON_PREFIX: &str = "reminder";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+  Done,
+  Snooze10Minutes,
+  Snooze1Hour,
+  SnoozeTomorrow,
+  /// Turn a one-shot reminder into a daily one
+  Repeat,
+}
+
+impl Action {
+  const ALL: [Action; 5] = [
+    Action::Done,
+    Action::Snooze10Minutes,
+    Action::Snooze1Hour,
+    Action::SnoozeTomorrow,
+    Action::Repeat,
+  ];
+
+  fn id(self) -> &'static str {
+    match self {
+      Action::Done => "done",
+      Action::Snooze10Minutes => "snooze10m",
+      Action::Snooze1Hour => "snooze1h",
+      Action::SnoozeTomorrow => "snoozetomorrow",
+      Action::Repeat => "repeat",
+    }
+  }
+
+  fn label(self) -> &'static str {
+    match self {
+      Action::Done => "Done",
+      Action::Snooze10Minutes => "Snooze 10m",
+      Action::Snooze1Hour => "Snooze 1h",
+      Action::SnoozeTomorrow => "Tomorrow",
+      Action::Repeat => "Repeat",
+    }
+  }
+}
+
+fn custom_id(action: Action, event_id: i32) -> String {
+  format!("{}:{}:{}", BUTTON_PREFIX, action.id(), event_id)
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<(Action, i32)> {
+  let mut parts = custom_id.split(':');
+  if parts.next()? != BUTTON_PREFIX {
+    return None;
+  }
+  let action = parts.next()?;
+  let action = Action::ALL
+    .iter()
+    .copied()
+    .find(|known| known.id() == action)?;
+  Some((action, parts.next()?.parse().ok()?))
+}
+
+pub fn reminder_buttons<'a>(
+  components: &'a mut CreateComponents,
+  event: &Event,
+) -> &'a mut CreateComponents {
+  components.create_action_row(|row| {
+    for action in Action::ALL {
+      // Repeating reminders already repeat
+      if action == Action::Repeat && event.recurrence.is_some() {
+        continue;
+      }
+      let style = match action {
+        Action::Done => ButtonStyle::Success,
+        _ => ButtonStyle::Secondary,
+      };
+      row.create_button(|button| {
+        button
+          .custom_id(custom_id(action, event.id))
+          .label(action.label())
+          .style(style)
+      });
+    }
+    row
+  })
+}
+
+/// Apply the action on the event and describe the result.
+fn apply_action(action: Action, event: &Event) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let now = date_parse::user_now(event.author as u64);
+  let snooze_until = match action {
+    Action::Done => None,
+    Action::Snooze10Minutes => Some(now + Duration::minutes(10)),
+    Action::Snooze1Hour => Some(now + Duration::hours(1)),
+    Action::SnoozeTomorrow => Some(date_parse::parse_date_time("tomorrow", now)?),
+    Action::Repeat => {
+      let trigger = now.timezone().from_utc_datetime(&event.trigger_date);
+      let recurrence: Recurrence = format!("every day {}", trigger.format("%H:%M")).parse()?;
+      let next = recurrence
+        .next_after(now.with_timezone(&Utc), now.timezone())
+        .ok_or("this recurrence never triggers")?;
+      let recurrence = recurrence.to_string();
+      let mut db_instance = INSTANCE.write().unwrap();
+      let event = db_instance.event_update(
+        event.id,
+        EventChangeset {
+          trigger_date: Some(next.naive_utc()),
+          recurrence: Some(Some(&recurrence)),
+          fired: Some(false),
+          ..Default::default()
+        },
+      )?;
+      scheduler::schedule(job_date(event), Job::Event(event.id));
+      return Ok(format!("🔁 Repeating {}", recurrence));
+    }
+  };
+
+  let mut db_instance = INSTANCE.write().unwrap();
+  let Some(until) = snooze_until else {
+    if event.fired {
+      db_instance.event_delete(event.id);
+    }
+    return Ok(String::from("✅ Done"));
+  };
+  let snoozed = if event.recurrence.is_some() {
+    // Keep the schedule of the repeating reminder, snooze a copy of this occurrence
+    db_instance.event_add(NewEvent {
+      author: event.author,
+      content: &event.content,
+      channel: event.channel,
+      trigger_date: until.naive_utc(),
+      recurrence: None,
+      mention: &event.mention,
+      dm: event.dm,
+      message_link: event.message_link.as_deref(),
+    })
+  } else {
+    db_instance.event_update(
+      event.id,
+      EventChangeset {
+        trigger_date: Some(until.naive_utc()),
+        fired: Some(false),
+        ..Default::default()
+      },
+    )?
+  };
+  scheduler::schedule(job_date(snoozed), Job::Event(snoozed.id));
+  Ok(format!(
+    "⏰ Snoozed until {}",
+    date_parse::format_date_time(&until)
+  ))
+}
+
+/// Handle a click on the buttons of a reminder, only its owner can use them.
+pub async fn handle_reminder_button(
+  ctx: &Context,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let Some((action, event_id)) = parse_custom_id(&component.data.custom_id) else {
+    return Ok(());
+  };
+  let event = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .events
+      .iter()
+      .find(|event| event.id == event_id)
+      .cloned()
+  };
+  let refusal = match &event {
+    None => Some("This reminder doesn't exist anymore"),
+    Some(event) if event.author != component.user.id.0 as i64 => {
+      Some("Only the owner of this reminder can use these buttons")
+    }
+    Some(_) => None,
+  };
+  if let Some(refusal) = refusal {
+    component
+      .create_interaction_response(&ctx.http, |response| {
+        response
+          .kind(InteractionResponseType::ChannelMessageWithSource)
+          .interaction_response_data(|data| data.content(refusal).ephemeral(true))
+      })
+      .await?;
+    return Ok(());
+  }
+
+  let status = apply_action(action, &event.unwrap())?;
+  let content = format!("{}\n{}", component.message.content, status);
+  component
+    .create_interaction_response(&ctx.http, |response| {
+      response
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|data| {
+          // An empty list of components removes the buttons
+          data.content(content).components(|components| components)
+        })
+    })
+    .await?;
+  Ok(())
+}
+
+#[test]
+fn test_parse_custom_id() {
+  for action in Action::ALL {
+    assert_eq!(parse_custom_id(&custom_id(action, 42)), Some((action, 42)));
+  }
+  assert_eq!(parse_custom_id("reminder:unknown:42"), None);
+  assert_eq!(parse_custom_id("other:done:42"), None);
+}
@@ -0,0 +1,461 @@
+mod buttons;
+pub mod recurrence;
+
+pub use self::buttons::handle_reminder_button;
+
+use self::recurrence::Recurrence;
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+    parse::{self, DiscordIds},
+    permissions,
+    scheduler::{self, Job},
+  },
+  database::{Event, EventChangeset, NewEvent, Role, INSTANCE},
+};
+use chrono::{prelude::*, Duration};
+use chrono_tz::Tz;
+use log::error;
+use procedural_macros::command;
+use serenity::{
+  http,
+  model::{
+    id::{ChannelId, RoleId, UserId},
+    Permissions,
+  },
+  prelude::Mentionable,
+};
+use std::{error::Error, fmt::Write};
+
+/// Parse the WHEN of a reminder: a date expression or a recurrence rule.
+///
+/// Returns the first trigger date and the recurrence rule of repeating reminders.
+pub fn parse_when(input: &str, timezone: Tz) -> Result<(DateTime<Tz>, Option<Recurrence>), String> {
+  let lowercase = input.trim().to_lowercase();
+  if lowercase.starts_with("every ") || lowercase.starts_with("cron ") {
+    let recurrence: Recurrence = lowercase.parse()?;
+    let first = recurrence
+      .next_after(Utc::now(), timezone)
+      .ok_or("this recurrence never triggers")?;
+    return Ok((first.with_timezone(&timezone), Some(recurrence)));
+  }
+  // Using the user time so we convert correctly when setting hours or minutes
+  // Paris.with_hour(10) => NaiveDateTime.hour == 8 because of Tz +2
+  let now = Utc::now().with_timezone(&timezone);
+  Ok((date_parse::parse_date_time(input, now)?, None))
+}
+
+fn check_content(content: &str) -> Result<(), String> {
+  if content.len() > 1900 {
+    return Err("Your message is too long".to_string());
+  }
+  Ok(())
+}
+
+/// Who receives a reminder.
+enum Target {
+  User(UserId),
+  Role(RoleId),
+  /// Posted in the channel without pinging anyone
+  Channel(ChannelId),
+}
+
+impl Target {
+  fn parse(input: &str, author: UserId) -> Result<Self, String> {
+    if input == "me" {
+      return Ok(Target::User(author));
+    }
+    Ok(match parse::discord_str_to_id(input, None)? {
+      (id, DiscordIds::User) => Target::User(UserId(id)),
+      (id, DiscordIds::Role) => Target::Role(RoleId(id)),
+      (id, DiscordIds::Channel) => Target::Channel(ChannelId(id)),
+      (_, kind) => return Err(format!("Can't send a reminder to a {}", kind)),
+    })
+  }
+
+  fn mention(&self) -> String {
+    match self {
+      Target::User(user) => user.mention().to_string(),
+      Target::Role(role) => role.mention().to_string(),
+      Target::Channel(_) => String::new(),
+    }
+  }
+}
+
+/// Permissions of the author in a channel of the guild the command was sent in.
+async fn author_permissions(params: &CallBackParams<'_>, channel: ChannelId) -> Permissions {
+  let guild = params
+    .message
+    .guild_id
+    .and_then(|guild_id| params.context.cache.guild(guild_id));
+  let Some(guild) = guild else {
+    return Permissions::empty();
+  };
+  let Ok(member) = guild.member(params.context, params.message.author.id).await else {
+    return Permissions::empty();
+  };
+  guild
+    .channels
+    .get(&channel)
+    .and_then(|channel| channel.clone().guild())
+    .and_then(|channel| guild.user_permissions_in(&channel, &member).ok())
+    .unwrap_or_else(Permissions::empty)
+}
+
+/// Roles can only be pinged when they are mentionable or by the members allowed to ping anyone,
+/// channels only by the members allowed to write in them.
+async fn check_target(params: &CallBackParams<'_>, target: &Target) -> Result<(), String> {
+  match target {
+    Target::User(_) => Ok(()),
+    Target::Role(role) => {
+      let mentionable = params
+        .message
+        .guild_id
+        .and_then(|guild_id| params.context.cache.role(guild_id, *role))
+        .is_some_and(|role| role.mentionable);
+      let permissions = author_permissions(params, params.message.channel_id).await;
+      if mentionable || permissions.contains(Permissions::MENTION_EVERYONE) {
+        Ok(())
+      } else {
+        Err(String::from("You are not allowed to ping this role"))
+      }
+    }
+    Target::Channel(channel) => {
+      let permissions = author_permissions(params, *channel).await;
+      if permissions.contains(Permissions::SEND_MESSAGES) {
+        Ok(())
+      } else {
+        Err(format!("You can't send messages in {}", channel.mention()))
+      }
+    }
+  }
+}
+
+/// Create a reminder from the WHEN, CONTENT and options ([dm] [<message link>]) arguments.
+///
+/// Replying to a message with the command attaches the reminder to that message.
+async fn add_reminder(params: &CallBackParams<'_>, target: Target) -> Result<String, String> {
+  let [when, content, options @ ..] = params.args else {
+    return Err(String::from("Missing WHEN or CONTENT"));
+  };
+  let (trigger_date, recurrence) =
+    parse_when(when, date_parse::user_timezone(params.message.author.id.0))?;
+  check_content(content)?;
+
+  let mut dm = false;
+  let mut message_link = match (&params.message.message_reference, params.message.guild_id) {
+    (Some(reference), Some(guild_id)) => reference
+      .message_id
+      .map(|message_id| parse::message_url(guild_id.0, reference.channel_id.0, message_id.0)),
+    _ => None,
+  };
+  for option in options {
+    if option == "dm" {
+      dm = true;
+    } else {
+      parse::message_url_to_ids(option)?;
+      message_link = Some(option.trim_matches(['<', '>']).to_string());
+    }
+  }
+  if dm && !matches!(target, Target::User(_)) {
+    return Err(String::from(
+      "Only the reminders for a user can be sent by DM",
+    ));
+  }
+  check_target(params, &target).await?;
+
+  let channel = match target {
+    Target::Channel(channel) => channel,
+    _ => params.message.channel_id,
+  };
+  let recurrence = recurrence.map(|recurrence| recurrence.to_string());
+  let mut db_instance = INSTANCE.write().unwrap();
+  let event = db_instance.event_add(NewEvent {
+    author: params.message.author.id.0 as i64,
+    channel: channel.0 as i64,
+    content,
+    trigger_date: trigger_date.naive_utc(),
+    recurrence: recurrence.as_deref(),
+    mention: &target.mention(),
+    dm,
+    message_link: message_link.as_deref(),
+  });
+  scheduler::schedule(trigger_date.with_timezone(&Utc), Job::Event(event.id));
+  Ok(format!(
+    "Reminder set for {}",
+    date_parse::format_date_time(&trigger_date)
+  ))
+}
+
+#[command]
+pub async fn remind_me(params: CallBackParams) -> CallbackReturn {
+  let target = Target::User(params.message.author.id);
+  let reminder_params = CallBackParams {
+    args: &params.args[1..],
+    ..params
+  };
+  Ok(Some(
+    add_reminder(&reminder_params, target)
+      .await
+      .unwrap_or_else(|error| error),
+  ))
+}
+
+#[command]
+pub async fn remind(params: CallBackParams) -> CallbackReturn {
+  let target = match Target::parse(&params.args[1], params.message.author.id) {
+    Ok(target) => target,
+    Err(error) => return Ok(Some(error)),
+  };
+  let reminder_params = CallBackParams {
+    args: &params.args[2..],
+    ..params
+  };
+  Ok(Some(
+    add_reminder(&reminder_params, target)
+      .await
+      .unwrap_or_else(|error| error),
+  ))
+}
+
+fn describe_event(event: &Event, timezone: Tz) -> String {
+  let next = timezone.from_utc_datetime(&event.trigger_date);
+  let destination = match (event.dm, event.mention.as_str()) {
+    (true, mention) => format!("by DM to {}", mention),
+    (false, "") => format!("in <#{}>", event.channel),
+    (false, mention) => format!("for {} in <#{}>", mention, event.channel),
+  };
+  format!(
+    "`{}` next on {} {} ({}): {}",
+    event.id,
+    date_parse::format_date_time(&next),
+    destination,
+    event.recurrence.as_deref().unwrap_or("once"),
+    event.content
+  )
+}
+
+/// Find a reminder the user is allowed to manage: its own, or any for admins.
+fn find_user_event<'a>(
+  events: &'a [Event],
+  event_id: &str,
+  user_id: UserId,
+  is_admin: bool,
+) -> Result<&'a Event, String> {
+  let event_id: i32 = event_id
+    .parse()
+    .map_err(|_| format!("Invalid reminder id: {}", event_id))?;
+  events
+    .iter()
+    .find(|event| event.id == event_id && !event.fired)
+    .filter(|event| event.author == user_id.0 as i64 || is_admin)
+    .ok_or_else(|| format!("Reminder {} not found", event_id))
+}
+
+const REMINDERS_USAGE: &str =
+  "Usage: @BOT reminders list | cancel <id> | edit <id> [when=<WHEN>] [content=<CONTENT>]";
+
+#[command]
+pub async fn reminders(params: CallBackParams) -> CallbackReturn {
+  let author = params.message.author.id;
+  // Read before locking the instance, both need it
+  let is_admin = permissions::user_role(author) >= Role::Admin;
+  let timezone = date_parse::user_timezone(author.0);
+  match (params.args[1].as_str(), params.args.len()) {
+    ("list", 2) => {
+      let db_instance = INSTANCE.read().unwrap();
+      let mut events: Vec<&Event> = db_instance
+        .events
+        .iter()
+        .filter(|event| event.author == author.0 as i64 && !event.fired)
+        .collect();
+      if events.is_empty() {
+        return Ok(Some(String::from("You have no reminder")));
+      }
+      events.sort_by_key(|event| event.trigger_date);
+      let mut reply = String::from("Your reminders:\n");
+      for event in events {
+        writeln!(reply, "{}", describe_event(event, timezone)).expect("unable to append string");
+      }
+      Ok(Some(reply))
+    }
+    ("cancel", 3) => {
+      let mut db_instance = INSTANCE.write().unwrap();
+      let event_id = match find_user_event(&db_instance.events, &params.args[2], author, is_admin) {
+        Ok(event) => event.id,
+        Err(error) => return Ok(Some(error)),
+      };
+      db_instance.event_delete(event_id);
+      Ok(Some(String::from(":ok:")))
+    }
+    ("edit", 4..) => {
+      let mut changes = EventChangeset::default();
+      let mut recurrence = None;
+      for change in &params.args[3..] {
+        match change.split_once('=') {
+          Some(("when", when)) => match parse_when(when, timezone) {
+            Ok((trigger_date, new_recurrence)) => {
+              changes.trigger_date = Some(trigger_date.naive_utc());
+              recurrence = Some(new_recurrence.map(|recurrence| recurrence.to_string()));
+            }
+            Err(error) => return Ok(Some(error)),
+          },
+          Some(("content", content)) => {
+            if let Err(error) = check_content(content) {
+              return Ok(Some(error));
+            }
+            changes.content = Some(content);
+          }
+          _ => {
+            return Ok(Some(format!(
+              "Unknown change: {}\n{}",
+              change, REMINDERS_USAGE
+            )))
+          }
+        }
+      }
+      changes.recurrence = recurrence.as_ref().map(Option::as_deref);
+
+      let mut db_instance = INSTANCE.write().unwrap();
+      let event_id = match find_user_event(&db_instance.events, &params.args[2], author, is_admin) {
+        Ok(event) => event.id,
+        Err(error) => return Ok(Some(error)),
+      };
+      let event = db_instance.event_update(event_id, changes)?;
+      scheduler::schedule(event.trigger_date.and_utc(), Job::Event(event.id));
+      Ok(Some(describe_event(event, timezone)))
+    }
+    _ => Ok(Some(String::from(REMINDERS_USAGE))),
+  }
+}
+
+/// How long a one-shot reminder can be snoozed after being sent.
+const SNOOZE_WINDOW_HOURS: i64 = 24;
+
+/// When the scheduler has to look at the event: its trigger, or the end of the snooze window.
+pub fn job_date(event: &Event) -> DateTime<Utc> {
+  match event.fired {
+    true => event.trigger_date.and_utc() + Duration::hours(SNOOZE_WINDOW_HOURS),
+    false => event.trigger_date.and_utc(),
+  }
+}
+
+async fn deliver_event(
+  http: &http::Http,
+  event: &Event,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut content = event.content.clone();
+  if let Some(link) = &event.message_link {
+    write!(content, "\n↪ {}", link).expect("unable to append string");
+  }
+  let (channel, content) = if event.dm {
+    let (user_id, _) = parse::discord_str_to_id(&event.mention, Some(DiscordIds::User))?;
+    let channel = UserId(user_id).create_dm_channel(http).await?.id;
+    (
+      channel,
+      format!("Reminder from <#{}>: {}", event.channel, content),
+    )
+  } else if event.mention.is_empty() {
+    (ChannelId(event.channel as u64), content)
+  } else {
+    (
+      ChannelId(event.channel as u64),
+      format!("{} {}", event.mention, content),
+    )
+  };
+  // Only the target of the reminder is notified, not the mentions written in its content
+  let target = parse::discord_str_to_id(&event.mention, None).ok();
+  channel
+    .send_message(http, |message| {
+      message
+        .content(content)
+        .allowed_mentions(|mentions| match target {
+          Some((id, DiscordIds::User)) => mentions.empty_parse().users(vec![UserId(id)]),
+          Some((id, DiscordIds::Role)) => mentions.empty_parse().roles(vec![RoleId(id)]),
+          _ => mentions.empty_parse(),
+        })
+        .components(|components| buttons::reminder_buttons(components, event))
+    })
+    .await?;
+  Ok(())
+}
+
+/// Tell the author of a reminder that it couldn't be sent to its target.
+async fn notify_undelivered(http: &http::Http, event: &Event, error: &(dyn Error + Send + Sync)) {
+  let notice = format!(
+    "Your reminder {} couldn't be sent: {}\nChange it with `reminders edit {}` or remove it with `reminders cancel {}`",
+    event.id, error, event.id, event.id
+  );
+  let notified = match UserId(event.author as u64).create_dm_channel(http).await {
+    Ok(dm) => dm.say(http, notice).await.map(|_| ()),
+    Err(e) => Err(e),
+  };
+  if let Err(e) = notified {
+    error!(
+      "Unable to tell {} about reminder {}: {}",
+      event.author, event.id, e
+    );
+  }
+}
+
+/// Send a reminder that is due, then reschedule it if it repeats or keep it to be snoozed.
+///
+/// Reminders cancelled or moved since they were scheduled are skipped, the ones sent and not
+/// snoozed are deleted at the end of the snooze window. A reminder that couldn't be sent stays
+/// pending and its author is told.
+pub async fn fire_event(
+  http: &http::Http,
+  event_id: i32,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let event = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .events
+      .iter()
+      .find(|event| event.id == event_id)
+      .cloned()
+  };
+  // Here we do not take the user time as it's already stored as Utc in the database
+  let Some(event) = event.filter(|event| job_date(event) <= Utc::now()) else {
+    return Ok(());
+  };
+  if event.fired {
+    INSTANCE.write().unwrap().event_delete(event_id);
+    return Ok(());
+  }
+
+  let sent = deliver_event(http, &event).await;
+  if let Err(error) = &sent {
+    notify_undelivered(http, &event, error.as_ref()).await;
+  }
+
+  let next = match event.recurrence.as_deref().map(str::parse::<Recurrence>) {
+    Some(Ok(recurrence)) => recurrence.next_from(
+      event.trigger_date.and_utc(),
+      Utc::now(),
+      date_parse::user_timezone(event.author as u64),
+    ),
+    Some(Err(e)) => {
+      error!("invalid recurrence of event {}: {}", event_id, e);
+      None
+    }
+    None => None,
+  };
+  let changes = match next {
+    Some(next) => EventChangeset {
+      trigger_date: Some(next.naive_utc()),
+      ..Default::default()
+    },
+    None if sent.is_err() => return sent,
+    None => EventChangeset {
+      fired: Some(true),
+      ..Default::default()
+    },
+  };
+  let mut db_instance = INSTANCE.write().unwrap();
+  let event = db_instance.event_update(event_id, changes)?;
+  scheduler::schedule(job_date(event), Job::Event(event_id));
+  sent?;
+  Ok(())
+}
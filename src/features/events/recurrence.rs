@@ -0,0 +1,485 @@
+//! Recurrence rules of repeating events, stored as text in the `recurrence` column of `events`.
+//!
+//! Supported forms:
+//!  * `every 2 weeks`, `every 30 minutes`: fixed interval since the previous trigger
+//!  * `every monday 9h`, `every lundi,jeudi 14h30`, `every weekday 18:00`, `every day 9h`
+//!  * `every working day 9h`, `every jour ouvré 9h`: weekdays that are not holidays or closure days
+//!  * `cron 0 9 * * 1-5`: standard 5 fields cron (minute hour day-of-month month day-of-week)
+use std::{fmt::Display, str::FromStr};
+
+use crate::core::{
+  business_days,
+  date_parse::{self, parse_time_of_day, parse_weekdays, ALL_DAYS, WEEKDAYS},
+};
+use chrono::{prelude::*, Duration};
+use chrono_tz::Tz;
+
+/// Smallest interval accepted, to avoid flooding a channel.
+const MIN_INTERVAL_MINUTES: i64 = 10;
+/// How far we look for the next match of a cron expression.
+const CRON_SEARCH_DAYS: u32 = 366 * 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntervalUnit {
+  Minute,
+  Hour,
+  Day,
+  Week,
+}
+
+impl IntervalUnit {
+  fn duration(self, count: u32) -> Duration {
+    let count = count as i64;
+    match self {
+      IntervalUnit::Minute => Duration::minutes(count),
+      IntervalUnit::Hour => Duration::hours(count),
+      IntervalUnit::Day => Duration::days(count),
+      IntervalUnit::Week => Duration::weeks(count),
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      IntervalUnit::Minute => "minute",
+      IntervalUnit::Hour => "hour",
+      IntervalUnit::Day => "day",
+      IntervalUnit::Week => "week",
+    }
+  }
+}
+
+impl FromStr for IntervalUnit {
+  type Err = String;
+
+  fn from_str(unit: &str) -> Result<Self, Self::Err> {
+    match unit {
+      "m" | "min" | "mins" | "minute" | "minutes" => Ok(IntervalUnit::Minute),
+      "h" | "hour" | "hours" | "heure" | "heures" => Ok(IntervalUnit::Hour),
+      "d" | "day" | "days" | "j" | "jour" | "jours" => Ok(IntervalUnit::Day),
+      "w" | "week" | "weeks" | "semaine" | "semaines" => Ok(IntervalUnit::Week),
+      _ => Err(format!("Unknown interval unit: {}", unit)),
+    }
+  }
+}
+
+/// A 5 fields cron expression, each field being the list of allowed values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+  expression: String,
+  minutes: Vec<u32>,
+  hours: Vec<u32>,
+  days_of_month: Vec<u32>,
+  months: Vec<u32>,
+  /// 0 is sunday, 7 is accepted as sunday too
+  days_of_week: Vec<u32>,
+  /// The standard cron rule: when both days fields are restricted, matching either is enough
+  days_restricted: (bool, bool),
+}
+
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+  let invalid = || format!("Invalid cron field: {}", field);
+  let mut values = Vec::new();
+  for part in field.split(',') {
+    let (range, step) = match part.split_once('/') {
+      Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+      None => (part, 1),
+    };
+    let (start, end) = match range {
+      "*" => (min, max),
+      _ => match range.split_once('-') {
+        Some((start, end)) => (
+          start.parse().map_err(|_| invalid())?,
+          end.parse().map_err(|_| invalid())?,
+        ),
+        None => {
+          let value = range.parse().map_err(|_| invalid())?;
+          (value, if part.contains('/') { max } else { value })
+        }
+      },
+    };
+    if step == 0 || start < min || end > max || start > end {
+      return Err(invalid());
+    }
+    values.extend((start..=end).step_by(step as usize));
+  }
+  values.sort_unstable();
+  values.dedup();
+  Ok(values)
+}
+
+impl FromStr for CronSchedule {
+  type Err = String;
+
+  fn from_str(expression: &str) -> Result<Self, Self::Err> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+      return Err(format!(
+        "A cron expression needs 5 fields (minute hour day month weekday), got: {}",
+        expression
+      ));
+    };
+    let mut days_of_week = parse_cron_field(days_of_week, 0, 7)?;
+    if days_of_week.contains(&7) {
+      days_of_week.retain(|day| *day != 7);
+      if !days_of_week.contains(&0) {
+        days_of_week.insert(0, 0);
+      }
+    }
+    let cron = CronSchedule {
+      expression: fields.join(" "),
+      minutes: parse_cron_field(minutes, 0, 59)?,
+      hours: parse_cron_field(hours, 0, 23)?,
+      days_of_month: parse_cron_field(days_of_month, 1, 31)?,
+      months: parse_cron_field(months, 1, 12)?,
+      days_of_week,
+      days_restricted: (fields[2] != "*", fields[4] != "*"),
+    };
+    if cron.shortest_gap() < Duration::minutes(MIN_INTERVAL_MINUTES) {
+      return Err(format!(
+        "The interval must be of at least {} minutes",
+        MIN_INTERVAL_MINUTES
+      ));
+    }
+    Ok(cron)
+  }
+}
+
+impl CronSchedule {
+  /// Shortest time between two triggers, as if every day matched.
+  fn shortest_gap(&self) -> Duration {
+    let times: Vec<i64> = self
+      .hours
+      .iter()
+      .flat_map(|hour| {
+        self
+          .minutes
+          .iter()
+          .map(move |minute| i64::from(hour * 60 + minute))
+      })
+      .collect();
+    let (Some(first), Some(last)) = (times.first(), times.last()) else {
+      return Duration::days(1);
+    };
+    let overnight = 24 * 60 - last + first;
+    let shortest = times
+      .windows(2)
+      .map(|pair| pair[1] - pair[0])
+      .fold(overnight, i64::min);
+    Duration::minutes(shortest)
+  }
+
+  fn matches_day(&self, date: NaiveDate) -> bool {
+    if !self.months.contains(&date.month()) {
+      return false;
+    }
+    let day_of_month = self.days_of_month.contains(&date.day());
+    let day_of_week = self
+      .days_of_week
+      .contains(&date.weekday().num_days_from_sunday());
+    match self.days_restricted {
+      (true, true) => day_of_month || day_of_week,
+      _ => day_of_month && day_of_week,
+    }
+  }
+
+  /// First matching local time strictly after `after`.
+  fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    let mut date = after.date();
+    for _ in 0..CRON_SEARCH_DAYS {
+      if self.matches_day(date) {
+        for hour in &self.hours {
+          for minute in &self.minutes {
+            let candidate = date.and_hms_opt(*hour, *minute, 0)?;
+            if candidate > after {
+              return Some(candidate);
+            }
+          }
+        }
+      }
+      date = date.succ_opt()?;
+    }
+    None
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+  /// Repeat every `count` `unit` since the previous trigger
+  Interval {
+    count: u32,
+    unit: IntervalUnit,
+  },
+  /// Repeat on the given days at a given local time
+  Weekly {
+    days: Vec<Weekday>,
+    time: NaiveTime,
+  },
+  /// Repeat on business days at a given local time, see [business_days]
+  BusinessDays {
+    time: NaiveTime,
+  },
+  Cron(CronSchedule),
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+  match day {
+    Weekday::Mon => "monday",
+    Weekday::Tue => "tuesday",
+    Weekday::Wed => "wednesday",
+    Weekday::Thu => "thursday",
+    Weekday::Fri => "friday",
+    Weekday::Sat => "saturday",
+    Weekday::Sun => "sunday",
+  }
+}
+
+impl FromStr for Recurrence {
+  type Err = String;
+
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    let input = input.trim().to_lowercase();
+    if let Some(expression) = input.strip_prefix("cron ") {
+      return Ok(Recurrence::Cron(expression.parse()?));
+    }
+    let Some(rule) = input.strip_prefix("every ") else {
+      return Err(format!(
+        "A recurrence starts with `every` or `cron`: {}",
+        input
+      ));
+    };
+    let words: Vec<&str> = rule.split_whitespace().collect();
+
+    // every 2 weeks | every 30m
+    let number_end = rule
+      .find(|c: char| !c.is_ascii_digit())
+      .unwrap_or(rule.len());
+    if number_end > 0 {
+      let count: u32 = rule[..number_end]
+        .parse()
+        .map_err(|_| format!("Invalid interval: {}", rule))?;
+      let unit: IntervalUnit = rule[number_end..].trim().parse()?;
+      if count == 0 || unit.duration(count) < Duration::minutes(MIN_INTERVAL_MINUTES) {
+        return Err(format!(
+          "The interval must be of at least {} minutes",
+          MIN_INTERVAL_MINUTES
+        ));
+      }
+      return Ok(Recurrence::Interval { count, unit });
+    }
+
+    // every working day 9h | every jour ouvré 18:00
+    let business_day_words = match words[..] {
+      ["workday" | "workdays", ..] => 1,
+      ["working" | "business", "day" | "days", ..]
+      | ["jour" | "jours", "ouvré" | "ouvrés" | "ouvre" | "ouvres", ..] => 2,
+      _ => 0,
+    };
+    // every monday,friday 9h | every weekday 18:00
+    let (days, time) = match words[business_day_words..] {
+      [] if business_day_words > 0 => ("", None),
+      [time] if business_day_words > 0 => ("", Some(time)),
+      [days] => (days, None),
+      [days, time] if business_day_words == 0 => (days, Some(time)),
+      _ => return Err(format!("Invalid recurrence: {}", input)),
+    };
+    let time = match time {
+      Some(time) => parse_time_of_day(time).ok_or_else(|| format!("Invalid time: {}", time))?,
+      None => NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+    };
+    if business_day_words > 0 {
+      return Ok(Recurrence::BusinessDays { time });
+    }
+    let mut weekdays = Vec::new();
+    for day in days.split(',') {
+      weekdays.extend(parse_weekdays(day).ok_or_else(|| format!("Unknown day: {}", day))?);
+    }
+    weekdays.sort_by_key(|day| day.num_days_from_monday());
+    weekdays.dedup();
+    Ok(Recurrence::Weekly {
+      days: weekdays,
+      time,
+    })
+  }
+}
+
+impl Display for Recurrence {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Recurrence::Interval { count, unit } => {
+        let plural = if *count > 1 { "s" } else { "" };
+        write!(f, "every {} {}{}", count, unit.name(), plural)
+      }
+      Recurrence::Weekly { days, time } => {
+        let days = if days[..] == ALL_DAYS {
+          String::from("day")
+        } else if days[..] == WEEKDAYS {
+          String::from("weekday")
+        } else {
+          days
+            .iter()
+            .map(|day| weekday_name(*day))
+            .collect::<Vec<_>>()
+            .join(",")
+        };
+        write!(f, "every {} {}", days, time.format("%H:%M"))
+      }
+      Recurrence::BusinessDays { time } => {
+        write!(f, "every working day {}", time.format("%H:%M"))
+      }
+      Recurrence::Cron(cron) => write!(f, "cron {}", cron.expression),
+    }
+  }
+}
+
+/// First business day at `time` strictly after `after`.
+fn next_business_time(
+  after: NaiveDateTime,
+  time: NaiveTime,
+  closures: &[NaiveDate],
+) -> NaiveDateTime {
+  let today = after.date();
+  if today.and_time(time) > after && business_days::is_business_day(today, closures) {
+    return today.and_time(time);
+  }
+  business_days::next_business_day(today, closures).and_time(time)
+}
+
+impl Recurrence {
+  /// Next trigger strictly after `after`, local times being computed in `timezone`.
+  pub fn next_after(&self, after: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+    let local_after = after.with_timezone(&timezone).naive_local();
+    let next_local = match self {
+      Recurrence::Interval { count, unit } => return Some(after + unit.duration(*count)),
+      Recurrence::Weekly { days, time } => (0..=7)
+        .filter_map(|offset| {
+          let date = local_after.date() + Duration::days(offset);
+          days.contains(&date.weekday()).then(|| date.and_time(*time))
+        })
+        .find(|candidate| *candidate > local_after)?,
+      Recurrence::BusinessDays { time } => {
+        let closures: Vec<NaiveDate> = business_days::closure_days()
+          .into_iter()
+          .map(|(date, _)| date)
+          .collect();
+        next_business_time(local_after, *time, &closures)
+      }
+      Recurrence::Cron(cron) => cron.next_after(local_after)?,
+    };
+    date_parse::from_local(timezone, next_local).map(|next| next.with_timezone(&Utc))
+  }
+
+  /// iCalendar `RRULE` of the recurrence, cron expressions have no equivalent.
+  ///
+  /// Holidays and closure days are not excluded from the business days rule.
+  pub fn to_rrule(&self) -> Option<String> {
+    let by_day = |days: &[Weekday]| {
+      days
+        .iter()
+        .map(|day| &weekday_name(*day)[..2])
+        .collect::<Vec<_>>()
+        .join(",")
+        .to_uppercase()
+    };
+    match self {
+      Recurrence::Interval { count, unit } => {
+        let frequency = match unit {
+          IntervalUnit::Minute => "MINUTELY",
+          IntervalUnit::Hour => "HOURLY",
+          IntervalUnit::Day => "DAILY",
+          IntervalUnit::Week => "WEEKLY",
+        };
+        Some(format!("FREQ={};INTERVAL={}", frequency, count))
+      }
+      Recurrence::Weekly { days, .. } => Some(format!("FREQ=WEEKLY;BYDAY={}", by_day(days))),
+      Recurrence::BusinessDays { .. } => Some(format!("FREQ=WEEKLY;BYDAY={}", by_day(&WEEKDAYS))),
+      Recurrence::Cron(_) => None,
+    }
+  }
+
+  /// Next trigger after the `previous` one that is in the future, skipping the ones missed.
+  pub fn next_from(
+    &self,
+    previous: DateTime<Utc>,
+    now: DateTime<Utc>,
+    timezone: Tz,
+  ) -> Option<DateTime<Utc>> {
+    match self {
+      Recurrence::Interval { .. } => {
+        let mut next = self.next_after(previous, timezone)?;
+        while next <= now {
+          next = self.next_after(next, timezone)?;
+        }
+        Some(next)
+      }
+      _ => self.next_after(previous.max(now), timezone),
+    }
+  }
+}
+
+#[test]
+fn test_recurrence_parse_display() {
+  for (input, expected) in [
+    ("every 2 weeks", "every 2 weeks"),
+    ("every 30m", "every 30 minutes"),
+    ("every 1 day", "every 1 day"),
+    ("every monday 9h", "every monday 09:00"),
+    ("every lundi,jeudi 14h30", "every monday,thursday 14:30"),
+    ("every weekday 18:00", "every weekday 18:00"),
+    ("Every day 9h", "every day 09:00"),
+    ("every jour ouvré 9h", "every working day 09:00"),
+    ("every business day", "every working day 09:00"),
+    ("cron 0 9 * * 1-5", "cron 0 9 * * 1-5"),
+  ] {
+    let recurrence: Recurrence = input.parse().unwrap();
+    assert_eq!(recurrence.to_string(), expected);
+    assert_eq!(expected.parse::<Recurrence>().unwrap(), recurrence);
+  }
+  let weekly: Recurrence = "every lundi,jeudi 14h30".parse().unwrap();
+  assert_eq!(weekly.to_rrule().unwrap(), "FREQ=WEEKLY;BYDAY=MO,TH");
+  assert!("every 5m".parse::<Recurrence>().is_err());
+  assert!("every someday".parse::<Recurrence>().is_err());
+  assert!("cron 0 25 * * *".parse::<Recurrence>().is_err());
+  assert!("cron * * * * *".parse::<Recurrence>().is_err());
+  assert!("cron */5 9 * * *".parse::<Recurrence>().is_err());
+  assert!("cron 55,0 8-9 * * *".parse::<Recurrence>().is_err());
+  assert!("cron */15 9-18 * * 1-5".parse::<Recurrence>().is_ok());
+  assert!("tomorrow".parse::<Recurrence>().is_err());
+}
+
+#[test]
+fn test_recurrence_next() {
+  use chrono_tz::Europe::Paris;
+
+  // Friday 2024-03-01 17:00 UTC => 18:00 in Paris
+  let friday = Utc.with_ymd_and_hms(2024, 3, 1, 17, 0, 0).unwrap();
+  let weekday: Recurrence = "every weekday 9h".parse().unwrap();
+  assert_eq!(
+    weekday.next_after(friday, Paris),
+    Some(Utc.with_ymd_and_hms(2024, 3, 4, 8, 0, 0).unwrap())
+  );
+  let monday: Recurrence = "every monday 18h".parse().unwrap();
+  assert_eq!(
+    monday.next_after(friday, Paris),
+    Some(Utc.with_ymd_and_hms(2024, 3, 4, 17, 0, 0).unwrap())
+  );
+  let cron: Recurrence = "cron 30 9 1 * *".parse().unwrap();
+  assert_eq!(
+    cron.next_after(friday, Paris),
+    Some(Utc.with_ymd_and_hms(2024, 4, 1, 7, 30, 0).unwrap())
+  );
+  // Wednesday 2025-04-30 18:00 => thursday 1st of may is a holiday
+  let wednesday = NaiveDate::from_ymd_opt(2025, 4, 30)
+    .unwrap()
+    .and_hms_opt(18, 0, 0)
+    .unwrap();
+  let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+  assert_eq!(
+    next_business_time(wednesday, nine, &[]),
+    NaiveDate::from_ymd_opt(2025, 5, 2).unwrap().and_time(nine)
+  );
+  let interval: Recurrence = "every 2 weeks".parse().unwrap();
+  assert_eq!(
+    interval.next_from(friday, friday + Duration::weeks(3), Paris),
+    Some(friday + Duration::weeks(4))
+  );
+}
@@ -0,0 +1,86 @@
+//! List the public holidays and manage the company closure days skipped by business day reminders.
+use std::{fmt::Write, time::SystemTime};
+
+use crate::{
+  core::{
+    business_days,
+    commands::{CallBackParams, CallbackReturn},
+    date_parse, permissions,
+  },
+  database::{NewStorage, Role, StorageDataType, INSTANCE},
+};
+use chrono::prelude::*;
+use procedural_macros::command;
+
+const HOLIDAYS_USAGE: &str = "Usage: @BOT holidays [<YEAR>] | add <DATE> [<LABEL>] | remove <DATE>";
+
+fn list_holidays(year: i32) -> String {
+  let mut days: Vec<(NaiveDate, String)> = business_days::french_holidays(year)
+    .into_iter()
+    .map(|(date, name)| (date, String::from(name)))
+    .chain(
+      business_days::closure_days()
+        .into_iter()
+        .filter(|(date, _)| date.year() == year)
+        .map(|(date, label)| (date, format!("{} (closure)", label))),
+    )
+    .collect();
+  days.sort();
+  let mut reply = format!("Days off in {}:\n", year);
+  for (date, name) in days {
+    writeln!(reply, "{}: {}", date.format("%A %d/%m/%Y"), name).expect("unable to append string");
+  }
+  reply
+}
+
+#[command]
+pub async fn holidays(params: CallBackParams) -> CallbackReturn {
+  let author = params.message.author.id;
+  let today = date_parse::user_now(author.0).date_naive();
+  let (action, date) = match params.args.get(1).map(String::as_str) {
+    None => return Ok(Some(list_holidays(today.year()))),
+    Some(action @ ("add" | "remove")) => match params.args.get(2) {
+      Some(date) => (action, date),
+      None => return Ok(Some(String::from(HOLIDAYS_USAGE))),
+    },
+    Some(year) => match year.parse() {
+      Ok(year) if params.args.len() == 2 => return Ok(Some(list_holidays(year))),
+      _ => return Ok(Some(String::from(HOLIDAYS_USAGE))),
+    },
+  };
+  if permissions::user_role(author) < Role::Admin {
+    return Ok(Some(String::from(
+      "Only admins can change the closure days",
+    )));
+  }
+  let Some(date) = date_parse::parse_day(date, today) else {
+    return Ok(Some(format!("Invalid date: {}", date)));
+  };
+
+  let mut db_instance = INSTANCE.write().unwrap();
+  let existing: Vec<i32> = db_instance
+    .filter_storage_type(StorageDataType::ClosureDay)
+    .into_iter()
+    .filter(|closure| closure.date.map(business_days::storage_date) == Some(date))
+    .map(|closure| closure.id)
+    .collect();
+  if action == "remove" {
+    if existing.is_empty() {
+      return Ok(Some(format!("{} is not a closure day", date)));
+    }
+    db_instance.storage_delete(existing);
+    return Ok(Some(String::from(":ok:")));
+  }
+  if !existing.is_empty() {
+    return Ok(Some(format!("{} is already a closure day", date)));
+  }
+  db_instance.storage_add(NewStorage {
+    datatype: StorageDataType::ClosureDay.into(),
+    dataid: None,
+    data: params.args.get(3).map_or("Company closure", String::as_str),
+    date: Some(SystemTime::from(
+      date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+    )),
+  });
+  Ok(Some(String::from(":ok:")))
+}
@@ -0,0 +1,609 @@
+//! iCalendar exports of the reminders of a user, and of the project deadlines and scheduled
+//! announcements of a guild.
+//!
+//! The files are uploaded by the `ics` command, or served over HTTP to subscribe from a calendar app:
+//!  * `/calendar/<token>.ics`: reminders of the owner of the token
+//!  * `/calendar/<token>.ics?guild=<id>`: deadlines and announcements of a guild of the owner, in
+//!    the channels the owner can read
+//!
+//! Tokens are personal, stored in `storage` and sent in DM by `ics link`. The server only runs when
+//! `HTTP_ADDRESS` is set.
+use std::{
+  borrow::Cow, collections::HashMap, env, error::Error, fmt::Write, sync::Arc, time::Duration,
+};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+  },
+  database::{NewStorage, ProjectStatus, StorageDataType, INSTANCE},
+  features::events::recurrence::Recurrence,
+};
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use log::{error, info};
+use procedural_macros::command;
+use rand::{distributions::Alphanumeric, Rng};
+use serenity::{
+  http::Http,
+  model::{
+    channel::{AttachmentType, GuildChannel},
+    id::{ChannelId, GuildId, UserId},
+    Permissions,
+  },
+};
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::{TcpListener, TcpStream},
+};
+
+const ICS_USAGE: &str = "Usage: @BOT ics [guild] | link [reset]";
+const TOKEN_LENGTH: usize = 32;
+/// Only the request line is needed, longer requests are refused.
+const MAX_REQUEST_SIZE: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+  /// Address the calendars are served on, nothing is served when it isn't set.
+  static ref HTTP_ADDRESS: Option<String> = env::var("HTTP_ADDRESS").ok().map(|address| bind_address(&address));
+  /// Base of the links sent to the users.
+  static ref PUBLIC_URL: String =
+    env::var("PUBLIC_URL").unwrap_or_else(|_| String::from("http://localhost:8080"));
+}
+
+struct IcsEvent {
+  uid: String,
+  /// Full `DTSTART` property, with its parameters
+  start: String,
+  /// Zone of `start` when it is in local time, described by a `VTIMEZONE`
+  timezone: Option<Tz>,
+  summary: String,
+  description: String,
+  rrule: Option<String>,
+}
+
+/// A port alone, as `8080` or `:8080`, is only served locally.
+fn bind_address(address: &str) -> String {
+  let port = address.strip_prefix(':').unwrap_or(address);
+  if port.parse::<u16>().is_ok() {
+    format!("127.0.0.1:{}", port)
+  } else {
+    address.to_string()
+  }
+}
+
+/// Escape a text value, see RFC 5545 3.3.11.
+fn escape(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace(';', "\\;")
+    .replace(',', "\\,")
+    .replace('\n', "\\n")
+}
+
+/// Fold a content line at 75 octets, see RFC 5545 3.1.
+fn fold(line: &str) -> String {
+  let mut folded = String::new();
+  let mut length = 0;
+  for character in line.chars() {
+    if length + character.len_utf8() > 75 {
+      folded.push_str("\r\n ");
+      length = 1;
+    }
+    folded.push(character);
+    length += character.len_utf8();
+  }
+  folded
+}
+
+/// `DTSTART` of a date, in UTC or in local time of `timezone` for recurring events.
+fn start_property(date: NaiveDateTime, timezone: Tz, recurring: bool) -> String {
+  if recurring {
+    // The rule is expanded in local time, to follow the daylight saving time
+    let local = timezone.from_utc_datetime(&date);
+    format!(
+      "DTSTART;TZID={}:{}",
+      timezone.name(),
+      local.format("%Y%m%dT%H%M%S")
+    )
+  } else {
+    format!("DTSTART:{}", date.format("%Y%m%dT%H%M%SZ"))
+  }
+}
+
+/// Seconds east of UTC of `timezone` at the UTC date `instant`.
+fn utc_offset(timezone: Tz, instant: NaiveDateTime) -> i32 {
+  timezone
+    .offset_from_utc_datetime(&instant)
+    .fix()
+    .local_minus_utc()
+}
+
+/// UTC offset as `+HHMM`, see RFC 5545 3.3.14.
+fn format_offset(seconds: i32) -> String {
+  let sign = if seconds < 0 { '-' } else { '+' };
+  let minutes = seconds.abs() / 60;
+  format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// Changes of offset of `timezone` during `year`: the UTC date, the offsets before and after.
+fn offset_changes(timezone: Tz, year: i32) -> Vec<(NaiveDateTime, i32, i32)> {
+  let new_year = |year| {
+    NaiveDate::from_ymd_opt(year, 1, 1)
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap()
+  };
+  let end = new_year(year + 1);
+  let mut changes = Vec::new();
+  let mut hour = new_year(year);
+  let mut offset = utc_offset(timezone, hour);
+  while hour < end {
+    let next = hour + chrono::Duration::hours(1);
+    let next_offset = utc_offset(timezone, next);
+    if next_offset != offset {
+      // Some zones change on the half hour
+      let mut instant = hour;
+      while utc_offset(timezone, instant) == offset {
+        instant += chrono::Duration::minutes(1);
+      }
+      changes.push((instant, offset, next_offset));
+      offset = next_offset;
+    }
+    hour = next;
+  }
+  changes
+}
+
+/// `VTIMEZONE` of a zone, see RFC 5545 3.6.5. The changes of offset of the previous year are
+/// repeated every year.
+fn timezone_lines(timezone: Tz) -> Vec<String> {
+  let year = Utc::now().year() - 1;
+  let mut lines = vec![
+    String::from("BEGIN:VTIMEZONE"),
+    format!("TZID:{}", timezone.name()),
+  ];
+  let changes = offset_changes(timezone, year);
+  if changes.is_empty() {
+    let start = NaiveDate::from_ymd_opt(year, 1, 1)
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap();
+    let offset = format_offset(utc_offset(timezone, start));
+    lines.extend([
+      String::from("BEGIN:STANDARD"),
+      format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")),
+      format!("TZOFFSETFROM:{}", offset),
+      format!("TZOFFSETTO:{}", offset),
+      String::from("END:STANDARD"),
+    ]);
+  }
+  let yearly = changes.len() == 2;
+  for (instant, from, to) in changes {
+    let kind = if to > from { "DAYLIGHT" } else { "STANDARD" };
+    // Local time before the change
+    let local = instant + chrono::Duration::seconds(from as i64);
+    let day = local.date();
+    let week = if (day + chrono::Duration::days(7)).month() != day.month() {
+      -1
+    } else {
+      (day.day() as i32 - 1) / 7 + 1
+    };
+    lines.push(format!("BEGIN:{}", kind));
+    lines.push(format!("DTSTART:{}", local.format("%Y%m%dT%H%M%S")));
+    if yearly {
+      lines.push(format!(
+        "RRULE:FREQ=YEARLY;BYMONTH={};BYDAY={}{}",
+        day.month(),
+        week,
+        day.weekday().to_string()[..2].to_uppercase()
+      ));
+    }
+    lines.push(format!("TZOFFSETFROM:{}", format_offset(from)));
+    lines.push(format!("TZOFFSETTO:{}", format_offset(to)));
+    lines.push(format!(
+      "TZNAME:{}",
+      timezone.from_utc_datetime(&instant).format("%Z")
+    ));
+    lines.push(format!("END:{}", kind));
+  }
+  lines.push(String::from("END:VTIMEZONE"));
+  lines
+}
+
+fn recurrence_rule(recurrence: Option<&str>) -> Option<String> {
+  recurrence?.parse::<Recurrence>().ok()?.to_rrule()
+}
+
+fn calendar(name: &str, events: Vec<IcsEvent>) -> String {
+  let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+  let mut lines = vec![
+    String::from("BEGIN:VCALENDAR"),
+    String::from("VERSION:2.0"),
+    String::from("PRODID:-//blackfoot//rbot-discord//EN"),
+    format!("X-WR-CALNAME:{}", escape(name)),
+  ];
+  // Every TZID needs its VTIMEZONE
+  let mut timezones: Vec<Tz> = Vec::new();
+  for timezone in events.iter().filter_map(|event| event.timezone) {
+    if !timezones.contains(&timezone) {
+      timezones.push(timezone);
+      lines.extend(timezone_lines(timezone));
+    }
+  }
+  for event in events {
+    lines.push(String::from("BEGIN:VEVENT"));
+    lines.push(format!("UID:{}", event.uid));
+    lines.push(format!("DTSTAMP:{}", stamp));
+    lines.push(event.start);
+    if let Some(rrule) = event.rrule {
+      lines.push(format!("RRULE:{}", rrule));
+    }
+    lines.push(format!("SUMMARY:{}", escape(&event.summary)));
+    lines.push(format!("DESCRIPTION:{}", escape(&event.description)));
+    lines.push(String::from("END:VEVENT"));
+  }
+  lines.push(String::from("END:VCALENDAR"));
+  let mut ics = String::new();
+  for line in lines {
+    write!(ics, "{}\r\n", fold(&line)).expect("unable to append string");
+  }
+  ics
+}
+
+/// Pending reminders created by a user.
+pub fn user_calendar(user_id: u64) -> String {
+  let timezone = date_parse::user_timezone(user_id);
+  let db_instance = INSTANCE.read().unwrap();
+  let events = db_instance
+    .events
+    .iter()
+    .filter(|event| event.author == user_id as i64 && !event.fired)
+    .map(|event| {
+      let rrule = recurrence_rule(event.recurrence.as_deref());
+      let mut summary: String = event.content.lines().next().unwrap_or_default().into();
+      if summary.is_empty() {
+        summary = String::from("Reminder");
+      }
+      IcsEvent {
+        uid: format!("reminder-{}@rbot", event.id),
+        start: start_property(event.trigger_date, timezone, rrule.is_some()),
+        timezone: rrule.is_some().then_some(timezone),
+        summary,
+        description: event.content.clone(),
+        rrule,
+      }
+    })
+    .collect();
+  calendar("Reminders", events)
+}
+
+/// Deadlines of the projects and scheduled announcements in the channels of a guild that the
+/// member `reader` can read.
+pub async fn guild_calendar(
+  http: &Http,
+  guild_id: GuildId,
+  reader: UserId,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let guild = guild_id.to_partial_guild(http).await?;
+  let member = guild_id.member(http, reader).await?;
+  let channels: HashMap<ChannelId, GuildChannel> = guild_id
+    .channels(http)
+    .await?
+    .into_iter()
+    .filter(|(_, channel)| {
+      guild
+        .user_permissions_in(channel, &member)
+        .is_ok_and(|permissions| permissions.contains(Permissions::VIEW_CHANNEL))
+    })
+    .collect();
+  let db_instance = INSTANCE.read().unwrap();
+  let mut events = Vec::new();
+  for project in &db_instance.projects {
+    if project.status() == ProjectStatus::Deleted {
+      continue;
+    }
+    let Some(channel) = channels.get(&ChannelId(project.channel_id as u64)) else {
+      continue;
+    };
+    let Some(deadline) = project.deadline_date else {
+      continue;
+    };
+    events.push(IcsEvent {
+      uid: format!("project-{}@rbot", project.id),
+      start: format!("DTSTART;VALUE=DATE:{}", deadline.format("%Y%m%d")),
+      timezone: None,
+      summary: format!("Deadline #{}", channel.name),
+      description: format!(
+        "Client: {}\nCodex: {}\nLead: {}",
+        project.client, project.codex, project.lead
+      ),
+      rrule: None,
+    });
+  }
+  for announcement in &db_instance.announcements {
+    let Some(channel) = channels.get(&ChannelId(announcement.channel as u64)) else {
+      continue;
+    };
+    let rrule = recurrence_rule(announcement.recurrence.as_deref());
+    let timezone = db_instance
+      .user_search(announcement.author as u64)
+      .and_then(|user| user.timezone.as_deref())
+      .and_then(|timezone| timezone.parse().ok())
+      .unwrap_or(date_parse::DEFAULT_TIMEZONE);
+    events.push(IcsEvent {
+      uid: format!("announcement-{}@rbot", announcement.id),
+      start: start_property(announcement.trigger_date, timezone, rrule.is_some()),
+      timezone: rrule.is_some().then_some(timezone),
+      summary: match &announcement.title {
+        Some(title) => format!("#{}: {}", channel.name, title),
+        None => format!("Announcement in #{}", channel.name),
+      },
+      description: announcement.content.clone(),
+      rrule,
+    });
+  }
+  Ok(calendar(&guild.name, events))
+}
+
+/// Token of the calendar links of a user, created on first use.
+fn user_token(user_id: u64, reset: bool) -> String {
+  let mut db_instance = INSTANCE.write().unwrap();
+  let existing: Vec<(i32, String)> = db_instance
+    .filter_storage_type(StorageDataType::CalendarToken)
+    .into_iter()
+    .filter(|token| token.dataid == Some(user_id as i64))
+    .map(|token| (token.id, token.data.clone()))
+    .collect();
+  match existing.first() {
+    Some((_, token)) if !reset => return token.clone(),
+    _ => db_instance.storage_delete(existing.iter().map(|(id, _)| *id).collect()),
+  };
+  let token: String = rand::thread_rng()
+    .sample_iter(&Alphanumeric)
+    .take(TOKEN_LENGTH)
+    .map(char::from)
+    .collect();
+  db_instance
+    .storage_add(NewStorage {
+      datatype: StorageDataType::CalendarToken.into(),
+      dataid: Some(user_id as i64),
+      data: &token,
+      date: None,
+    })
+    .data
+    .clone()
+}
+
+/// Compare all the bytes whatever the first difference, so the time taken tells nothing of a token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn token_user(token: &str) -> Option<UserId> {
+  let db_instance = INSTANCE.read().unwrap();
+  let mut owner = None;
+  for stored in db_instance.filter_storage_type(StorageDataType::CalendarToken) {
+    if constant_time_eq(stored.data.as_bytes(), token.as_bytes()) {
+      owner = stored.dataid;
+    }
+  }
+  owner.map(|user_id| UserId(user_id as u64))
+}
+
+#[command]
+pub async fn ics(params: CallBackParams) -> CallbackReturn {
+  let author = &params.message.author;
+  let http = &params.context.http;
+  match (params.args.get(1).map(String::as_str), params.args.get(2)) {
+    (None, _) => {
+      let export = user_calendar(author.id.0);
+      author
+        .direct_message(http, |message| {
+          message.add_file(AttachmentType::Bytes {
+            data: Cow::from(export.into_bytes()),
+            filename: String::from("reminders.ics"),
+          })
+        })
+        .await?;
+      Ok(Some(String::from(":ok:")))
+    }
+    (Some("guild"), None) => {
+      let Some(guild_id) = params.message.guild_id else {
+        return Ok(Some(String::from("This command only works in a server")));
+      };
+      // Sent in DM, the calendar only holds the channels the author can read
+      let export = guild_calendar(http, guild_id, author.id).await?;
+      author
+        .direct_message(http, |message| {
+          message.add_file(AttachmentType::Bytes {
+            data: Cow::from(export.into_bytes()),
+            filename: format!("{}.ics", guild_id),
+          })
+        })
+        .await?;
+      Ok(Some(String::from(":ok:")))
+    }
+    (Some("link"), reset) if reset.is_none_or(|reset| reset == "reset") => {
+      if HTTP_ADDRESS.is_none() {
+        return Ok(Some(String::from(
+          "Calendar links are disabled, HTTP_ADDRESS is not set",
+        )));
+      }
+      let token = user_token(author.id.0, reset.is_some());
+      let mut content = format!("Your reminders: {}/calendar/{}.ics", *PUBLIC_URL, token);
+      if let Some(guild_id) = params.message.guild_id {
+        write!(
+          content,
+          "\nThis server: {}/calendar/{}.ics?guild={}",
+          *PUBLIC_URL, token, guild_id
+        )
+        .expect("unable to append string");
+      }
+      content.push_str("\nKeep these links private, `ics link reset` revokes them");
+      author
+        .direct_message(http, |message| message.content(content))
+        .await?;
+      Ok(Some(String::from(":ok:")))
+    }
+    _ => Ok(Some(String::from(ICS_USAGE))),
+  }
+}
+
+/// Calendar requested by an HTTP request target, or the status of the error.
+async fn calendar_response(http: &Http, target: &str) -> Result<String, &'static str> {
+  let (path, query) = target.split_once('?').unwrap_or((target, ""));
+  let token = path
+    .strip_prefix("/calendar/")
+    .and_then(|file| file.strip_suffix(".ics"))
+    .ok_or("404 Not Found")?;
+  let user_id = token_user(token).ok_or("404 Not Found")?;
+  if query.is_empty() {
+    return Ok(user_calendar(user_id.0));
+  }
+  let guild_id = query
+    .strip_prefix("guild=")
+    .and_then(|guild_id| guild_id.parse().ok())
+    .map(GuildId)
+    .ok_or("400 Bad Request")?;
+  // Only the members of a guild see its calendar
+  guild_id
+    .member(http, user_id)
+    .await
+    .map_err(|_| "403 Forbidden")?;
+  guild_calendar(http, guild_id, user_id).await.map_err(|e| {
+    error!("ics: unable to export guild {}: {}", guild_id, e);
+    "500 Internal Server Error"
+  })
+}
+
+async fn handle_connection(
+  mut stream: TcpStream,
+  http: Arc<Http>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut request = vec![0; MAX_REQUEST_SIZE];
+  let mut length = 0;
+  while !request[..length].windows(2).any(|end| end == b"\r\n") && length < request.len() {
+    let read = tokio::time::timeout(REQUEST_TIMEOUT, stream.read(&mut request[length..])).await??;
+    if read == 0 {
+      break;
+    }
+    length += read;
+  }
+  let request = String::from_utf8_lossy(&request[..length]);
+  let request_line: Vec<&str> = request
+    .lines()
+    .next()
+    .unwrap_or_default()
+    .split(' ')
+    .collect();
+  let response = match request_line[..] {
+    ["GET", target, _] => calendar_response(&http, target).await,
+    _ => Err("400 Bad Request"),
+  };
+  let (status, content_type, body) = match response {
+    Ok(body) => ("200 OK", "text/calendar; charset=utf-8", body),
+    Err(status) => (status, "text/plain; charset=utf-8", String::from(status)),
+  };
+  let response = format!(
+    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    content_type,
+    body.len(),
+    body
+  );
+  stream.write_all(response.as_bytes()).await?;
+  stream.shutdown().await?;
+  Ok(())
+}
+
+/// Serve the calendars over HTTP forever, when `HTTP_ADDRESS` is set.
+pub async fn serve(http: Arc<Http>) {
+  let Some(address) = HTTP_ADDRESS.as_deref() else {
+    info!("HTTP_ADDRESS is not set, calendars are not served");
+    return;
+  };
+  let listener = match TcpListener::bind(address).await {
+    Ok(listener) => listener,
+    Err(e) => {
+      error!("ics: unable to listen on {}: {}", address, e);
+      return;
+    }
+  };
+  info!("serving calendars on {}", address);
+  loop {
+    match listener.accept().await {
+      Ok((stream, _)) => {
+        let http = http.clone();
+        tokio::spawn(async move {
+          if let Err(e) = handle_connection(stream, http).await {
+            error!("ics: {}", e);
+          }
+        });
+      }
+      Err(e) => error!("ics: unable to accept a connection: {}", e),
+    }
+  }
+}
+
+#[test]
+fn test_calendar_format() {
+  let ics = calendar(
+    "Test",
+    vec![IcsEvent {
+      uid: String::from("reminder-1@rbot"),
+      start: start_property(
+        NaiveDate::from_ymd_opt(2026, 10, 19)
+          .unwrap()
+          .and_hms_opt(7, 0, 0)
+          .unwrap(),
+        chrono_tz::Europe::Paris,
+        true,
+      ),
+      timezone: Some(chrono_tz::Europe::Paris),
+      summary: String::from("Standup; daily, really"),
+      description: "é".repeat(60),
+      rrule: Some(String::from("FREQ=DAILY;INTERVAL=1")),
+    }],
+  );
+  assert!(ics.contains("\r\nDTSTART;TZID=Europe/Paris:20261019T090000\r\n"));
+  assert_eq!(
+    ics
+      .matches("BEGIN:VTIMEZONE\r\nTZID:Europe/Paris\r\n")
+      .count(),
+    1
+  );
+  assert!(ics.contains("\r\nSUMMARY:Standup\\; daily\\, really\r\n"));
+  assert!(ics.lines().all(|line| line.len() <= 76));
+  assert!(ics.ends_with("END:VCALENDAR\r\n"));
+}
+
+#[test]
+fn test_timezone_lines() {
+  let paris = timezone_lines(chrono_tz::Europe::Paris).join("\n");
+  assert!(paris.contains(
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\nTZOFFSETFROM:+0100\nTZOFFSETTO:+0200\nTZNAME:CEST"
+  ));
+  assert!(paris.contains(
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\nTZOFFSETFROM:+0200\nTZOFFSETTO:+0100\nTZNAME:CET"
+  ));
+  let tokyo = timezone_lines(chrono_tz::Asia::Tokyo).join("\n");
+  assert!(tokyo.contains("BEGIN:STANDARD\n"));
+  assert!(tokyo.contains("TZOFFSETFROM:+0900\nTZOFFSETTO:+0900\n"));
+  assert_eq!(format_offset(-(3 * 3600 + 30 * 60)), "-0330");
+}
+
+#[test]
+fn test_bind_address() {
+  assert_eq!(bind_address("8080"), "127.0.0.1:8080");
+  assert_eq!(bind_address(":8080"), "127.0.0.1:8080");
+  assert_eq!(bind_address("0.0.0.0:8080"), "0.0.0.0:8080");
+}
+
+#[test]
+fn test_constant_time_eq() {
+  assert!(constant_time_eq(b"token", b"token"));
+  assert!(!constant_time_eq(b"token", b"tokem"));
+  assert!(!constant_time_eq(b"token", b"toke"));
+}
@@ -0,0 +1,170 @@
+use std::fmt::Write;
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse, parse, permissions,
+  },
+  database::{Role, INSTANCE},
+  features::search,
+};
+use chrono_tz::Tz;
+use procedural_macros::command;
+use serenity::{
+  model::id::{GuildId, UserId},
+  prelude::*,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum DiffOp {
+  Same,
+  Removed,
+  Added,
+}
+
+/// Longest common subsequence between the words of `old` and `new`.
+fn diff_words<'a>(old: &'a str, new: &'a str) -> Vec<(DiffOp, &'a str)> {
+  let old: Vec<&str> = old.split_whitespace().collect();
+  let new: Vec<&str> = new.split_whitespace().collect();
+
+  // lcs[i][j] is the lcs length of old[i..] and new[j..]
+  let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+  for i in (0..old.len()).rev() {
+    for j in (0..new.len()).rev() {
+      lcs[i][j] = if old[i] == new[j] {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+
+  let (mut i, mut j) = (0, 0);
+  let mut diff = Vec::new();
+  while i < old.len() && j < new.len() {
+    if old[i] == new[j] {
+      diff.push((DiffOp::Same, old[i]));
+      i += 1;
+      j += 1;
+    } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+      diff.push((DiffOp::Removed, old[i]));
+      i += 1;
+    } else {
+      diff.push((DiffOp::Added, new[j]));
+      j += 1;
+    }
+  }
+  diff.extend(old[i..].iter().map(|word| (DiffOp::Removed, *word)));
+  diff.extend(new[j..].iter().map(|word| (DiffOp::Added, *word)));
+  diff
+}
+
+/// Render the word diff with ~~removed~~ and __added__ words.
+fn word_diff(old: &str, new: &str) -> String {
+  let mut groups: Vec<(DiffOp, Vec<&str>)> = Vec::new();
+  for (op, word) in diff_words(old, new) {
+    match groups.last_mut() {
+      Some((last_op, words)) if *last_op == op => words.push(word),
+      _ => groups.push((op, vec![word])),
+    }
+  }
+  groups
+    .into_iter()
+    .map(|(op, words)| {
+      let words = words.join(" ");
+      match op {
+        DiffOp::Same => words,
+        DiffOp::Removed => format!("~~{}~~", words),
+        DiffOp::Added => format!("__{}__", words),
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn format_date(date: Option<std::time::SystemTime>, timezone: Tz) -> String {
+  date
+    .map(|date| date_parse::format_user_date_time(date, timezone))
+    .unwrap_or_else(|| String::from("unknown date"))
+}
+
+/// Original content and edits of a message, restricted to moderators and the author of the message
+/// and to the users still able to read its channel.
+pub async fn message_history_report(
+  context: &Context,
+  guild_id: Option<u64>,
+  message_id: u64,
+  requester: UserId,
+) -> String {
+  let timezone = date_parse::user_timezone(requester.0);
+  let is_moderator = permissions::user_role(requester) >= Role::Moderator;
+  let guild = guild_id.map(GuildId).unwrap_or_else(parse::main_guild_id);
+  let readable = search::readable_channels(context, guild, requester).await;
+  let db_instance = INSTANCE.read().unwrap();
+  let Some((message, edits)) = db_instance.message_history(message_id as i64) else {
+    return String::from("I don't know this message");
+  };
+  if message.author != requester.0 as i64 && !is_moderator {
+    return String::from("Only moderators and the author can see the history of a message");
+  }
+  if !readable.contains(&(message.channel as u64)) {
+    return String::from("You can't read the channel of this message");
+  }
+
+  let link = match guild_id {
+    Some(guild_id) => parse::message_url(guild_id, message.channel as u64, message.id as u64),
+    None => message.id.to_string(),
+  };
+  let mut report = format!("History of <{}> by <@{}>:\n", link, message.author);
+  writeln!(
+    report,
+    "`{}` **original**: {}",
+    format_date(message.date, timezone),
+    message.content
+  )
+  .expect("unable to append string");
+  if edits.is_empty() {
+    report.push_str("This message was never edited");
+  }
+  let mut previous = &message.content;
+  for (index, edit) in edits.iter().enumerate() {
+    writeln!(
+      report,
+      "`{}` **edit {}**: {}",
+      format_date(edit.date, timezone),
+      index + 1,
+      word_diff(previous, &edit.content)
+    )
+    .expect("unable to append string");
+    previous = &edit.content;
+  }
+  report
+}
+
+#[command]
+pub async fn history(params: CallBackParams) -> CallbackReturn {
+  let (guild_id, _, message_id) = match parse::message_url_to_ids(&params.args[1]) {
+    Ok(ids) => ids,
+    Err(error) => return Ok(Some(error)),
+  };
+  Ok(Some(
+    message_history_report(
+      params.context,
+      guild_id,
+      message_id,
+      params.message.author.id,
+    )
+    .await,
+  ))
+}
+
+#[test]
+fn test_word_diff() {
+  assert_eq!(word_diff("hello world", "hello world"), "hello world");
+  assert_eq!(
+    word_diff("the quick brown fox", "the slow brown fox jumps"),
+    "the ~~quick~~ __slow__ brown fox __jumps__"
+  );
+  assert_eq!(word_diff("a b c", ""), "~~a b c~~");
+  assert_eq!(word_diff("", "a b"), "__a b__");
+}
@@ -10,23 +10,34 @@
 // pub mod airtable;
 // pub mod gitlab_preview;
 
+pub mod absences;
+pub mod announcements;
 pub mod anyone;
 pub mod archivage;
 pub mod calendar;
+pub mod categories;
 pub mod deployment;
 pub mod emoji;
 pub mod events;
 pub mod funny;
 pub mod gemini;
+pub mod holidays;
+pub mod ics;
 
 pub mod invite_action;
 pub mod mecleanup;
+pub mod message_history;
 pub mod minecraft;
 pub mod ordering;
+pub mod personal_data;
 pub mod project_manager;
 pub mod renaming;
+pub mod retention;
+pub mod search;
 pub mod threadcontrol;
+pub mod timezone;
 
+use crate::core::scheduler;
 use log::info;
 use serenity::{http, prelude::TypeMapKey};
 use std::sync::Arc;
@@ -53,6 +64,8 @@ impl Features {
   pub fn run(&mut self, http: &Arc<http::Http>) {
     info!("Running features");
     let http_clone = http.clone();
-    tokio::spawn(async { events::check_events_loop(http_clone).await });
+    tokio::spawn(async { scheduler::run(http_clone).await });
+    let http_clone = http.clone();
+    tokio::spawn(async { ics::serve(http_clone).await });
   }
 }
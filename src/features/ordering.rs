@@ -15,7 +15,7 @@ use serenity::{
   prelude::*,
 };
 
-use super::archivage::filter_guild_channel;
+use super::{archivage::filter_guild_channel, categories};
 
 #[command]
 pub async fn ordering_channel_command(params: CallBackParams) -> CallbackReturn {
@@ -43,13 +43,7 @@ fn ordering_channels_type(
 ) -> (String, Vec<ChannelId>) {
   let mut channels: Vec<_> = channels
     .iter()
-    .filter(|chan| {
-      chan.kind == chantype
-        && match chan.parent_id {
-          Some(chan) => chan == category,
-          None => category == 0,
-        }
-    })
+    .filter(|chan| chan.kind == chantype && categories::in_category(chan.parent_id, category))
     .collect();
   channels.sort_by(|chan, chan2| chan.name.cmp(&chan2.name));
   let mut display = String::new();
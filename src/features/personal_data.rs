@@ -0,0 +1,71 @@
+use std::{borrow::Cow, time::SystemTime};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    parse::{self, DiscordIds},
+  },
+  database::{NewStorage, StorageDataType, INSTANCE},
+  features::project_manager,
+};
+use log::info;
+use procedural_macros::command;
+use serenity::model::{channel::AttachmentType, id::UserId};
+
+#[command]
+pub async fn my_data(params: CallBackParams) -> CallbackReturn {
+  if params.args[1] != "export" {
+    return Ok(Some(format!("Unknown action: {}", params.args[1])));
+  }
+  let author = &params.message.author;
+  let export = {
+    let db_instance = INSTANCE.read().unwrap();
+    serde_json::to_vec_pretty(&db_instance.user_data(author.id.0 as i64)?)?
+  };
+  author
+    .direct_message(&params.context.http, |message| {
+      message
+        .content("Here is everything i have stored about you")
+        .add_file(AttachmentType::Bytes {
+          data: Cow::from(export),
+          filename: format!("{}-data.json", author.id),
+        })
+    })
+    .await?;
+  Ok(Some(String::from(":ok:")))
+}
+
+#[command]
+pub async fn forget(params: CallBackParams) -> CallbackReturn {
+  let user_id = match parse::discord_str_to_id(&params.args[1], Some(DiscordIds::User)) {
+    Ok((user_id, _)) => user_id as i64,
+    Err(error) => return Ok(Some(error)),
+  };
+  // Before erasing, the reconciliation of the members would record them again otherwise
+  project_manager::revoke_all_access(&params.context.http, UserId(user_id as u64)).await?;
+
+  let mut db_instance = INSTANCE.write().unwrap();
+  let report = db_instance.user_forget(user_id)?;
+  let summary = format!(
+    "Erased by {}: {} messages, {} edits, {} events, {} storage entries, {} absences, {} project memberships, {} access requests, {} announcements, {} project changes, {} user",
+    params.message.author.id,
+    report.messages,
+    report.messages_edits,
+    report.events,
+    report.storage,
+    report.absences,
+    report.project_members,
+    report.project_access_requests,
+    report.announcements,
+    report.project_history,
+    report.user
+  );
+  info!("Forget {} => {}", user_id, summary);
+  db_instance.storage_add(NewStorage {
+    datatype: StorageDataType::Forgotten.into(),
+    dataid: Some(user_id),
+    data: &summary,
+    date: Some(SystemTime::now()),
+  });
+  Ok(Some(summary))
+}
@@ -0,0 +1,475 @@
+//! Access mode of the projects: open to anyone reacting to the announcement or picking the project
+//! in the directory, open after the approval of the lead, or closed.
+//!
+//! Requests are posted in the project channel with buttons for the lead, the permission overwrite
+//! is only created once approved.
+use std::{error::Error, fmt::Write, str::FromStr};
+
+use super::{
+  deadlines::lead_mention,
+  find_project, is_project_lead,
+  members::{record_join, record_leave},
+  refresh_directory,
+  templates::project_channel_ids,
+};
+use crate::{
+  core::{
+    commands::CallBackParams,
+    date_parse, permissions,
+    permissions::{member_channel_read, ReadState},
+  },
+  database::{
+    AccessRequestStatus, MemberSource, NewProjectAccessRequest, NewProjectChange, Project,
+    ProjectAccess, ProjectAccessRequest, ProjectChangeset, ProjectStatus, Role, INSTANCE,
+  },
+};
+use log::error;
+use serenity::{
+  builder::CreateComponents,
+  http::Http,
+  model::{
+    application::{
+      component::ButtonStyle,
+      interaction::{message_component::MessageComponentInteraction, InteractionResponseType},
+    },
+    channel::PermissionOverwriteType,
+    id::{ChannelId, UserId},
+  },
+  prelude::*,
+};
+
+const ACCESS_USAGE: &str = "Usage: @BOT project access [#channel] [open|approval|closed]";
+/// Prefix of the custom id of the buttons, followed by the decision and the request.
+const BUTTON_PREFIX: &str = "access_request";
+
+/// What happened when a user asked to join a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinOutcome {
+  Joined,
+  Requested,
+  AlreadyRequested,
+  Closed,
+}
+
+impl JoinOutcome {
+  pub fn describe(self, channel_id: i64) -> String {
+    match self {
+      JoinOutcome::Joined => format!("Joined <#{}>", channel_id),
+      JoinOutcome::Requested => format!(
+        "Your request to join <#{}> was sent to its lead",
+        channel_id
+      ),
+      JoinOutcome::AlreadyRequested => format!(
+        "Your request to join <#{}> is waiting for its lead",
+        channel_id
+      ),
+      JoinOutcome::Closed => format!("<#{}> is closed, ask its lead to add you", channel_id),
+    }
+  }
+}
+
+/// What a join would do, the projects archived or deleted are closed whatever their mode.
+fn join_outcome(
+  access: ProjectAccess,
+  status: ProjectStatus,
+  already_requested: bool,
+) -> JoinOutcome {
+  if matches!(status, ProjectStatus::Archived | ProjectStatus::Deleted) {
+    return JoinOutcome::Closed;
+  }
+  match access {
+    ProjectAccess::Open => JoinOutcome::Joined,
+    ProjectAccess::Closed => JoinOutcome::Closed,
+    ProjectAccess::Approval if already_requested => JoinOutcome::AlreadyRequested,
+    ProjectAccess::Approval => JoinOutcome::Requested,
+  }
+}
+
+/// Let the user read all the channels of the project and record it as a member.
+pub async fn grant_access(
+  http: &Http,
+  project: &Project,
+  user_id: UserId,
+  source: MemberSource,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let main_channel = ChannelId(project.channel_id as u64);
+  let overwrite = member_channel_read(user_id, ReadState::Allow);
+  for channel_id in project_channel_ids(main_channel) {
+    channel_id.create_permission(http, &overwrite).await?;
+  }
+  record_join(main_channel, user_id, source);
+  Ok(())
+}
+
+fn pending_request(project_id: i32, user_id: UserId) -> Option<i32> {
+  let db_instance = INSTANCE.read().unwrap();
+  db_instance
+    .project_access_requests
+    .iter()
+    .find(|request| {
+      request.project_id == project_id
+        && request.user_id == user_id.0 as i64
+        && request.status() == AccessRequestStatus::Pending
+    })
+    .map(|request| request.id)
+}
+
+/// Give access to the project, or ask its lead, following its access mode.
+pub async fn join_project(
+  http: &Http,
+  project: &Project,
+  user_id: UserId,
+  source: MemberSource,
+) -> Result<JoinOutcome, Box<dyn Error + Send + Sync>> {
+  let already_requested = pending_request(project.id, user_id).is_some();
+  match join_outcome(project.access(), project.status(), already_requested) {
+    JoinOutcome::Requested => (),
+    JoinOutcome::Joined => {
+      grant_access(http, project, user_id, source).await?;
+      return Ok(JoinOutcome::Joined);
+    }
+    outcome => return Ok(outcome),
+  }
+  let request_id = {
+    let mut db_instance = INSTANCE.write().unwrap();
+    db_instance
+      .project_access_request_add(NewProjectAccessRequest {
+        project_id: project.id,
+        user_id: user_id.0 as i64,
+        source: &source.to_string(),
+      })
+      .id
+  };
+  let lead = lead_mention(http, &project.lead).await;
+  ChannelId(project.channel_id as u64)
+    .send_message(http, |message| {
+      message
+        .content(format!("{} <@{}> asks to join this project", lead, user_id))
+        .components(|components| {
+          components.create_action_row(|row| {
+            row
+              .create_button(|button| {
+                button
+                  .custom_id(button_id(AccessRequestStatus::Approved, request_id))
+                  .label("Approve")
+                  .style(ButtonStyle::Success)
+              })
+              .create_button(|button| {
+                button
+                  .custom_id(button_id(AccessRequestStatus::Denied, request_id))
+                  .label("Deny")
+                  .style(ButtonStyle::Danger)
+              })
+          })
+        })
+    })
+    .await?;
+  Ok(JoinOutcome::Requested)
+}
+
+/// Remove the user from all the channels of the project and withdraw its pending request.
+pub async fn leave_project(
+  http: &Http,
+  project: &Project,
+  user_id: UserId,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let main_channel = ChannelId(project.channel_id as u64);
+  for channel_id in project_channel_ids(main_channel) {
+    channel_id
+      .delete_permission(http, PermissionOverwriteType::Member(user_id))
+      .await?;
+  }
+  record_leave(main_channel, user_id);
+  if let Some(request_id) = pending_request(project.id, user_id) {
+    let mut db_instance = INSTANCE.write().unwrap();
+    db_instance.project_access_request_decide(request_id, AccessRequestStatus::Cancelled, None)?;
+  }
+  Ok(())
+}
+
+fn button_id(decision: AccessRequestStatus, request_id: i32) -> String {
+  let decision = match decision {
+    AccessRequestStatus::Approved => "approve",
+    _ => "deny",
+  };
+  format!("{}:{}:{}", BUTTON_PREFIX, decision, request_id)
+}
+
+/// The decision and the request of a button made by [button_id].
+fn parse_button_id(custom_id: &str) -> Option<(AccessRequestStatus, i32)> {
+  let (decision, request_id) = custom_id
+    .strip_prefix(BUTTON_PREFIX)?
+    .strip_prefix(':')?
+    .split_once(':')?;
+  let decision = match decision {
+    "approve" => AccessRequestStatus::Approved,
+    "deny" => AccessRequestStatus::Denied,
+    _ => return None,
+  };
+  Some((decision, request_id.parse().ok()?))
+}
+
+async fn reply_ephemeral(
+  ctx: &Context,
+  component: &MessageComponentInteraction,
+  content: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  component
+    .create_interaction_response(&ctx.http, |response| {
+      response
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|data| data.content(content).ephemeral(true))
+    })
+    .await?;
+  Ok(())
+}
+
+/// Approve or deny an access request from the buttons posted in the project channel.
+pub async fn handle_access_button(
+  ctx: &Context,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let Some((mut decision, request_id)) = parse_button_id(&component.data.custom_id) else {
+    return Ok(());
+  };
+  let found = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .project_access_requests
+      .iter()
+      .find(|request| request.id == request_id)
+      .and_then(|request| {
+        let project = db_instance
+          .projects
+          .iter()
+          .find(|project| project.id == request.project_id)?;
+        Some((request.clone(), project.clone()))
+      })
+  };
+  let Some((request, project)) = found else {
+    return reply_ephemeral(ctx, component, "This request no longer exists").await;
+  };
+  let decider = &component.user;
+  if permissions::user_role(decider.id) < Role::Admin
+    && !is_project_lead(ChannelId(project.channel_id as u64), decider)
+  {
+    return reply_ephemeral(
+      ctx,
+      component,
+      "Only admins and the lead of the project can answer this request",
+    )
+    .await;
+  }
+  if request.status() != AccessRequestStatus::Pending {
+    let content = format!("This request is already {}", request.status);
+    return reply_ephemeral(ctx, component, &content).await;
+  }
+
+  // The project may have been closed, archived or deleted since the request
+  if decision == AccessRequestStatus::Approved
+    && join_outcome(project.access(), project.status(), false) == JoinOutcome::Closed
+  {
+    decision = AccessRequestStatus::Cancelled;
+  }
+  let requester = UserId(request.user_id as u64);
+  // The request stays pending, and can be answered again, until the access is given
+  if decision == AccessRequestStatus::Approved {
+    let source = MemberSource::from_str(&request.source).unwrap_or(MemberSource::Reaction);
+    grant_access(&ctx.http, &project, requester, source).await?;
+  }
+  {
+    let mut db_instance = INSTANCE.write().unwrap();
+    db_instance.project_access_request_decide(request.id, decision, Some(decider.id.0 as i64))?;
+  }
+  component
+    .create_interaction_response(&ctx.http, |response| {
+      response
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|data| {
+          data
+            .content(format!(
+              "<@{}> asked to join this project: {} by <@{}>",
+              requester, decision, decider.id
+            ))
+            .set_components(CreateComponents::default())
+        })
+    })
+    .await?;
+  let notice = format!(
+    "Your request to join <#{}> was {}",
+    project.channel_id, decision
+  );
+  let notified = match requester.create_dm_channel(&ctx.http).await {
+    Ok(dm) => dm.say(&ctx.http, notice).await.map(|_| ()),
+    Err(e) => Err(e),
+  };
+  if let Err(e) = notified {
+    error!("Unable to notify {} of the decision: {}", requester, e);
+  }
+  Ok(())
+}
+
+fn describe_access(
+  project: &Project,
+  requests: &[ProjectAccessRequest],
+  timezone: chrono_tz::Tz,
+) -> String {
+  let mut reply = format!("<#{}> access: {}", project.channel_id, project.access());
+  let pending = requests.iter().filter(|request| {
+    request.project_id == project.id && request.status() == AccessRequestStatus::Pending
+  });
+  for request in pending {
+    write!(
+      reply,
+      "\n<@{}> waiting since {}",
+      request.user_id,
+      date_parse::format_user_date_time(request.requested_at.and_utc(), timezone)
+    )
+    .expect("unable to append string");
+  }
+  reply
+}
+
+pub async fn access(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let (project, args) = match find_project(params.message, &params.args[2..]) {
+    Ok(found) => found,
+    Err(error) => return Ok(error),
+  };
+  let author = &params.message.author;
+  let mode = match args {
+    [] => {
+      let timezone = date_parse::user_timezone(author.id.0);
+      let db_instance = INSTANCE.read().unwrap();
+      return Ok(describe_access(
+        &project,
+        &db_instance.project_access_requests,
+        timezone,
+      ));
+    }
+    [mode] => match ProjectAccess::from_str(mode) {
+      Ok(mode) => mode,
+      Err(_) => return Ok(String::from(ACCESS_USAGE)),
+    },
+    _ => return Ok(String::from(ACCESS_USAGE)),
+  };
+  if permissions::user_role(author.id) < Role::Admin
+    && !is_project_lead(ChannelId(project.channel_id as u64), author)
+  {
+    return Ok(String::from(
+      "Only admins and the lead of the project can change its access",
+    ));
+  }
+  if project.access() == mode {
+    return Ok(format!("<#{}> is already {}", project.channel_id, mode));
+  }
+  let new_access = mode.to_string();
+  {
+    let mut db_instance = INSTANCE.write().unwrap();
+    db_instance.project_update(
+      project.id,
+      ProjectChangeset {
+        access: Some(&new_access),
+        ..Default::default()
+      },
+    )?;
+    db_instance.project_history_add(&[NewProjectChange {
+      project_id: project.id,
+      author: author.id.0 as i64,
+      field: "access",
+      old_value: &project.access,
+      new_value: &new_access,
+    }])?;
+  }
+  refresh_directory(&params.context.http).await;
+  Ok(String::from(":ok:"))
+}
+
+#[test]
+fn test_join_outcome() {
+  use ProjectAccess::*;
+  assert_eq!(
+    join_outcome(Open, ProjectStatus::Active, false),
+    JoinOutcome::Joined
+  );
+  assert_eq!(
+    join_outcome(Open, ProjectStatus::OnHold, false),
+    JoinOutcome::Joined
+  );
+  assert_eq!(
+    join_outcome(Approval, ProjectStatus::Active, false),
+    JoinOutcome::Requested
+  );
+  assert_eq!(
+    join_outcome(Approval, ProjectStatus::Active, true),
+    JoinOutcome::AlreadyRequested
+  );
+  assert_eq!(
+    join_outcome(Closed, ProjectStatus::Active, false),
+    JoinOutcome::Closed
+  );
+  assert_eq!(
+    join_outcome(Open, ProjectStatus::Archived, false),
+    JoinOutcome::Closed
+  );
+  assert_eq!(
+    join_outcome(Approval, ProjectStatus::Deleted, true),
+    JoinOutcome::Closed
+  );
+}
+
+#[test]
+fn test_parse_button_id() {
+  for decision in [AccessRequestStatus::Approved, AccessRequestStatus::Denied] {
+    assert_eq!(
+      parse_button_id(&button_id(decision, 42)),
+      Some((decision, 42))
+    );
+  }
+  assert_eq!(parse_button_id("access_request:cancel:42"), None);
+  assert_eq!(parse_button_id("access_request:approve:x"), None);
+  assert_eq!(parse_button_id("reminder:done:42"), None);
+}
+
+#[test]
+fn test_describe_access() {
+  let project = Project {
+    id: 1,
+    message_id: 0,
+    channel_id: 10,
+    codex: String::new(),
+    client: String::new(),
+    lead: String::new(),
+    deadline: String::new(),
+    description: String::new(),
+    contexte: String::new(),
+    created_at: std::time::SystemTime::UNIX_EPOCH,
+    pinned_message_id: None,
+    status: ProjectStatus::Active.to_string(),
+    deleted_at: None,
+    deadline_date: None,
+    access: ProjectAccess::Approval.to_string(),
+  };
+  let request = |id, project_id, status: AccessRequestStatus| ProjectAccessRequest {
+    id,
+    project_id,
+    user_id: 100 + id as i64,
+    source: MemberSource::Directory.to_string(),
+    status: status.to_string(),
+    requested_at: chrono::NaiveDate::from_ymd_opt(2026, 10, 19)
+      .unwrap()
+      .and_hms_opt(8, 0, 0)
+      .unwrap(),
+    decided_by: None,
+    decided_at: None,
+  };
+  let requests = [
+    request(1, 1, AccessRequestStatus::Pending),
+    request(2, 1, AccessRequestStatus::Denied),
+    request(3, 2, AccessRequestStatus::Pending),
+  ];
+  let description = describe_access(&project, &requests, chrono_tz::UTC);
+  assert!(description.starts_with("<#10> access: approval"));
+  assert!(description.contains("<@101> waiting since"));
+  assert!(!description.contains("<@102>"));
+  assert!(!description.contains("<@103>"));
+}
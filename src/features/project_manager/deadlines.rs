@@ -0,0 +1,324 @@
+//! Deadlines of the projects: reminders in the project channel some days before, the list of the
+//! upcoming and overdue ones, and a weekly summary in the announcement channel.
+//!
+//! Reminders are scheduled for each configured offset, a reminder checks when it fires that the
+//! deadline and the offsets didn't change since.
+use std::{error::Error, fmt::Write};
+
+use crate::{
+  constants::discordids::PROJECT_ANOUNCEMENT_CHANNEL,
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+    parse::{self, DiscordIds},
+    permissions,
+    scheduler::{self, Job},
+  },
+  database::{
+    NewStorage, Project, ProjectChangeset, ProjectStatus, Role, StorageDataType, INSTANCE,
+  },
+  features::events::recurrence::Recurrence,
+};
+use chrono::{prelude::*, Duration};
+use procedural_macros::command;
+use serenity::{http::Http, model::id::ChannelId};
+
+const DEADLINES_USAGE: &str = "Usage: @BOT deadlines [overdue] | reminders [<DAYS>...]";
+/// Days before the deadline when it is reminded, unless configured with `deadlines reminders`.
+const DEFAULT_REMINDERS: [u32; 3] = [7, 1, 0];
+/// Local time of the reminders and of the weekly summary.
+const REMINDER_HOUR: u32 = 9;
+/// Days shown by the weekly summary.
+const SUMMARY_DAYS: i64 = 7;
+/// Members fetched when looking for the lead by name.
+const MEMBER_SEARCH_LIMIT: u64 = 10;
+
+/// Parse the new deadline of `changes`, if any, into the date used by the reminders.
+pub fn set_deadline_date(changes: &mut ProjectChangeset, today: NaiveDate) {
+  if let Some(deadline) = changes.deadline {
+    changes.deadline_date = Some(parse_deadline(deadline, today));
+  }
+}
+
+/// Deadline written in a fiche, a date or a day relative to `today` followed by any text.
+pub fn parse_deadline(deadline: &str, today: NaiveDate) -> Option<NaiveDate> {
+  let deadline = deadline.trim();
+  date_parse::parse_day(deadline, today).or_else(|| {
+    let first_word = deadline.split_whitespace().next()?;
+    date_parse::parse_day(first_word, today)
+  })
+}
+
+/// Days before a deadline when it is reminded.
+fn reminder_offsets() -> Vec<u32> {
+  let db_instance = INSTANCE.read().unwrap();
+  match db_instance.find_storage_type(StorageDataType::DeadlineReminders) {
+    Some(stored) => stored
+      .data
+      .split(',')
+      .filter_map(|days| days.parse().ok())
+      .collect(),
+    None => DEFAULT_REMINDERS.to_vec(),
+  }
+}
+
+fn local_morning(day: NaiveDate) -> DateTime<Utc> {
+  date_parse::DEFAULT_TIMEZONE
+    .from_local_datetime(&day.and_hms_opt(REMINDER_HOUR, 0, 0).unwrap())
+    .earliest()
+    .expect("the morning always exists")
+    .with_timezone(&Utc)
+}
+
+fn today() -> NaiveDate {
+  Utc::now()
+    .with_timezone(&date_parse::DEFAULT_TIMEZONE)
+    .date_naive()
+}
+
+/// Only the active projects have their deadline followed.
+fn followed(project: &Project) -> Option<NaiveDate> {
+  project
+    .deadline_date
+    .filter(|_| project.status() == ProjectStatus::Active)
+}
+
+/// Queue the reminders of a project that are still to come.
+pub fn schedule_reminders(project: &Project) {
+  let Some(deadline) = followed(project) else {
+    return;
+  };
+  let now = Utc::now();
+  for days_before in reminder_offsets() {
+    let date = local_morning(deadline - Duration::days(days_before.into()));
+    if date > now {
+      scheduler::schedule(date, Job::DeadlineReminder(project.id, days_before));
+    }
+  }
+}
+
+/// Queue the reminders of all the projects.
+pub fn schedule_all_reminders() {
+  let projects = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance.projects.clone()
+  };
+  for project in &projects {
+    schedule_reminders(project);
+  }
+}
+
+/// The lead as a mention when it is a mention or the exact name of a single member of the guild,
+/// or else as written in the fiche.
+pub async fn lead_mention(http: &Http, lead: &str) -> String {
+  if let Ok((user_id, _)) = parse::discord_str_to_id(lead, Some(DiscordIds::User)) {
+    return format!("<@{}>", user_id);
+  }
+  let name = lead.trim().trim_start_matches('@').to_lowercase();
+  if name.is_empty() {
+    return lead.to_string();
+  }
+  let Ok(members) = parse::main_guild_id()
+    .search_members(http, &name, Some(MEMBER_SEARCH_LIMIT))
+    .await
+  else {
+    return lead.to_string();
+  };
+  // The search matches the start of the names, only an exact match is the lead
+  let matching: Vec<_> = members
+    .iter()
+    .filter(|member| {
+      member.user.name.to_lowercase() == name
+        || member
+          .nick
+          .as_deref()
+          .is_some_and(|nick| nick.to_lowercase() == name)
+    })
+    .collect();
+  match matching.as_slice() {
+    [member] => format!("<@{}>", member.user.id),
+    _ => lead.to_string(),
+  }
+}
+
+fn describe_days_left(days_before: i64) -> String {
+  match days_before {
+    0 => String::from("today"),
+    1 => String::from("tomorrow"),
+    days => format!("in {} days", days),
+  }
+}
+
+/// Remind the deadline in the project channel, if it is still `days_before` days away.
+pub async fn reminder_job(
+  http: &Http,
+  project_id: i32,
+  days_before: u32,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let project = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .projects
+      .iter()
+      .find(|project| project.id == project_id)
+      .cloned()
+  };
+  let Some((project, deadline)) = project.and_then(|project| {
+    let deadline = followed(&project)?;
+    Some((project, deadline))
+  }) else {
+    return Ok(());
+  };
+  let days_left = (deadline - today()).num_days();
+  if days_left != i64::from(days_before) || !reminder_offsets().contains(&days_before) {
+    return Ok(());
+  }
+  let lead = lead_mention(http, &project.lead).await;
+  ChannelId(project.channel_id as u64)
+    .say(
+      http,
+      format!(
+        "{} Deadline {}: {}",
+        lead,
+        describe_days_left(days_left),
+        deadline.format("%A %d/%m/%Y")
+      ),
+    )
+    .await?;
+  Ok(())
+}
+
+/// One line per followed project whose deadline is within `range`, the earliest first.
+fn deadline_lines(range: impl Fn(NaiveDate) -> bool) -> Vec<String> {
+  let db_instance = INSTANCE.read().unwrap();
+  let mut projects: Vec<(NaiveDate, &Project)> = db_instance
+    .projects
+    .iter()
+    .filter_map(|project| Some((followed(project)?, project)))
+    .filter(|(deadline, _)| range(*deadline))
+    .collect();
+  projects.sort_by_key(|(deadline, _)| *deadline);
+  projects
+    .into_iter()
+    .map(|(deadline, project)| {
+      format!(
+        "{} <#{}> · Lead: {}",
+        deadline.format("%d/%m/%Y"),
+        project.channel_id,
+        project.lead
+      )
+    })
+    .collect()
+}
+
+/// Date of the next weekly summary, on monday morning.
+pub fn next_summary_date() -> DateTime<Utc> {
+  let summary = Recurrence::Weekly {
+    days: vec![Weekday::Mon],
+    time: NaiveTime::from_hms_opt(REMINDER_HOUR, 0, 0).unwrap(),
+  };
+  summary
+    .next_after(Utc::now(), date_parse::DEFAULT_TIMEZONE)
+    .expect("there is always a next monday")
+}
+
+/// Post the deadlines of the week and the overdue ones, then schedule the next summary.
+pub async fn summary_job(http: &Http) -> Result<(), Box<dyn Error + Send + Sync>> {
+  scheduler::schedule(next_summary_date(), Job::DeadlineSummary);
+  let today = today();
+  let upcoming =
+    deadline_lines(|deadline| deadline >= today && deadline < today + Duration::days(SUMMARY_DAYS));
+  let overdue = deadline_lines(|deadline| deadline < today);
+  if upcoming.is_empty() && overdue.is_empty() {
+    return Ok(());
+  }
+  let mut summary = String::new();
+  if !upcoming.is_empty() {
+    writeln!(summary, "Deadlines this week:\n{}", upcoming.join("\n"))
+      .expect("unable to append string");
+  }
+  if !overdue.is_empty() {
+    writeln!(summary, "Overdue:\n{}", overdue.join("\n")).expect("unable to append string");
+  }
+  for message in parse::split_reply(&summary) {
+    ChannelId(PROJECT_ANOUNCEMENT_CHANNEL)
+      .say(http, message)
+      .await?;
+  }
+  Ok(())
+}
+
+fn set_reminders(params: &CallBackParams<'_>) -> String {
+  let days = &params.args[2..];
+  if days.is_empty() {
+    let offsets: Vec<String> = reminder_offsets().iter().map(u32::to_string).collect();
+    return format!("Deadlines are reminded {} days before", offsets.join(", "));
+  }
+  if permissions::user_role(params.message.author.id) < Role::Admin {
+    return String::from("Only admins can change the reminders");
+  }
+  let mut offsets = Vec::new();
+  for day in days {
+    match day.trim_start_matches(['J', 'j', '-']).parse::<u32>() {
+      Ok(day) => offsets.push(day),
+      Err(_) => return format!("Invalid number of days: {}", day),
+    }
+  }
+  offsets.sort_unstable_by(|a, b| b.cmp(a));
+  offsets.dedup();
+  let data: Vec<String> = offsets.iter().map(u32::to_string).collect();
+  {
+    let mut db_instance = INSTANCE.write().unwrap();
+    let existing = db_instance
+      .filter_storage_type(StorageDataType::DeadlineReminders)
+      .into_iter()
+      .map(|stored| stored.id)
+      .collect();
+    db_instance.storage_delete(existing);
+    db_instance.storage_add(NewStorage {
+      datatype: StorageDataType::DeadlineReminders.into(),
+      dataid: None,
+      data: &data.join(","),
+      date: None,
+    });
+  }
+  schedule_all_reminders();
+  String::from(":ok:")
+}
+
+#[command]
+pub async fn deadlines(params: CallBackParams) -> CallbackReturn {
+  let today = today();
+  let reply = match params.args.get(1).map(String::as_str) {
+    None => {
+      let upcoming = deadline_lines(|deadline| deadline >= today);
+      if upcoming.is_empty() {
+        String::from("No upcoming deadline")
+      } else {
+        format!("Upcoming deadlines:\n{}", upcoming.join("\n"))
+      }
+    }
+    Some("overdue") if params.args.len() == 2 => {
+      let overdue = deadline_lines(|deadline| deadline < today);
+      if overdue.is_empty() {
+        String::from("No overdue project")
+      } else {
+        format!("Overdue projects:\n{}", overdue.join("\n"))
+      }
+    }
+    Some("reminders") => set_reminders(&params),
+    _ => String::from(DEADLINES_USAGE),
+  };
+  Ok(Some(reply))
+}
+
+#[test]
+fn test_parse_deadline() {
+  let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+  let day = |month, day| NaiveDate::from_ymd_opt(2026, month, day);
+  assert_eq!(parse_deadline("15/11/2026", today), day(11, 15));
+  assert_eq!(parse_deadline(" 2026-11-15 ", today), day(11, 15));
+  assert_eq!(parse_deadline("15/11 (v1)", today), day(11, 15));
+  assert_eq!(parse_deadline("N/A", today), None);
+  assert_eq!(parse_deadline("", today), None);
+}
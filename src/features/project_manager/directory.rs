@@ -0,0 +1,331 @@
+//! Directory of the projects in the announcement channel, with select menus to join or leave any
+//! project channel.
+//!
+//! The directory is posted with `project directory` and edited whenever a project is created,
+//! renamed, edited, archived or deleted. Its messages are kept in the storage.
+use std::error::Error;
+
+use super::access::{join_project, leave_project};
+use crate::{
+  constants::discordids::PROJECT_ANOUNCEMENT_CHANNEL,
+  core::parse,
+  database::{
+    MemberSource, NewStorage, Project, ProjectAccess, ProjectStatus, StorageDataType, INSTANCE,
+  },
+};
+use log::error;
+use serenity::{
+  builder::{CreateComponents, CreateEmbed},
+  http::Http,
+  model::{
+    application::interaction::{
+      message_component::MessageComponentInteraction, InteractionResponseType,
+    },
+    id::{ChannelId, UserId},
+  },
+  prelude::*,
+};
+
+/// Prefix of the custom id of the menus, followed by the index of the menu.
+const MENU_PREFIX: &str = "directory";
+/// Discord limits: options of a menu, menus of a message and length of the option texts.
+const OPTIONS_PER_MENU: usize = 25;
+const MENUS_PER_MESSAGE: usize = 5;
+const OPTION_TEXT_LENGTH: usize = 100;
+
+lazy_static! {
+  /// Refreshes run one at a time so the messages are not posted twice.
+  static ref REFRESH: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+struct DirectoryEntry {
+  channel_id: ChannelId,
+  name: String,
+  details: String,
+}
+
+fn truncate(text: &str) -> String {
+  text.chars().take(OPTION_TEXT_LENGTH).collect()
+}
+
+fn describe_project(project: &Project) -> String {
+  let mut details = Vec::new();
+  if !project.client.is_empty() && project.client != "N/A" {
+    details.push(format!("Client: {}", project.client));
+  }
+  details.push(format!("Lead: {}", project.lead));
+  if project.status() == ProjectStatus::OnHold {
+    details.push(String::from("on hold"));
+  }
+  if project.access() == ProjectAccess::Approval {
+    details.push(String::from("approval required"));
+  }
+  details.join(" · ")
+}
+
+/// Projects that can be joined or requested, sorted by channel name.
+async fn directory_entries(
+  http: &Http,
+) -> Result<Vec<DirectoryEntry>, Box<dyn Error + Send + Sync>> {
+  let channels = parse::main_guild_id().channels(http).await?;
+  let db_instance = INSTANCE.read().unwrap();
+  let mut entries: Vec<DirectoryEntry> = db_instance
+    .projects
+    .iter()
+    .filter(|project| {
+      matches!(
+        project.status(),
+        ProjectStatus::Active | ProjectStatus::OnHold
+      ) && project.access() != ProjectAccess::Closed
+    })
+    .filter_map(|project| {
+      let channel = channels.get(&ChannelId(project.channel_id as u64))?;
+      Some(DirectoryEntry {
+        channel_id: channel.id,
+        name: channel.name.clone(),
+        details: describe_project(project),
+      })
+    })
+    .collect();
+  entries.sort_by(|a, b| a.name.cmp(&b.name));
+  Ok(entries)
+}
+
+/// One embed and its menus per message of the directory.
+fn directory_messages(entries: &[DirectoryEntry]) -> Vec<(CreateEmbed, CreateComponents)> {
+  let messages: Vec<&[DirectoryEntry]> = entries
+    .chunks(OPTIONS_PER_MENU * MENUS_PER_MESSAGE)
+    .collect();
+  let count = messages.len().max(1);
+  let mut directory = Vec::new();
+  for index in 0..count {
+    let mut embed = CreateEmbed::default();
+    embed.title(if count > 1 {
+      format!("Project directory ({}/{})", index + 1, count)
+    } else {
+      String::from("Project directory")
+    });
+    let mut components = CreateComponents::default();
+    match messages.get(index) {
+      Some(message_entries) => {
+        embed.description("Pick a project to join its channel, pick it again to leave it.");
+        for (menu_index, menu) in message_entries.chunks(OPTIONS_PER_MENU).enumerate() {
+          let first = &menu[0].name;
+          let last = &menu[menu.len() - 1].name;
+          components.create_action_row(|row| {
+            row.create_select_menu(|select| {
+              select
+                .custom_id(format!(
+                  "{}:{}",
+                  MENU_PREFIX,
+                  index * MENUS_PER_MESSAGE + menu_index
+                ))
+                .placeholder(truncate(&format!("Join or leave: {} … {}", first, last)))
+                .min_values(1)
+                .max_values(menu.len() as u64)
+                .options(|options| {
+                  for entry in menu {
+                    options.create_option(|option| {
+                      option
+                        .label(truncate(&format!("#{}", entry.name)))
+                        .value(entry.channel_id)
+                        .description(truncate(&entry.details))
+                    });
+                  }
+                  options
+                })
+            })
+          });
+        }
+      }
+      None => {
+        embed.description("No project to join");
+      }
+    }
+    embed.footer(|footer| footer.text(format!("{} projects", entries.len())));
+    directory.push((embed, components));
+  }
+  directory
+}
+
+/// Ids of the storage entries and messages of the directory, in order.
+fn directory_message_ids() -> Vec<(i32, u64)> {
+  let db_instance = INSTANCE.read().unwrap();
+  let mut message_ids: Vec<(i32, u64)> = db_instance
+    .filter_storage_type(StorageDataType::ProjectDirectory)
+    .into_iter()
+    .filter_map(|stored| Some((stored.id, stored.dataid? as u64)))
+    .collect();
+  message_ids.sort_unstable();
+  message_ids
+}
+
+/// Edit the messages of the directory, posting or deleting the ones needed for the projects.
+async fn update_directory(http: &Http) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let message_ids = directory_message_ids();
+  let entries = directory_entries(http).await?;
+  let messages = directory_messages(&entries);
+  let channel = ChannelId(PROJECT_ANOUNCEMENT_CHANNEL);
+  for (index, (embed, components)) in messages.iter().enumerate() {
+    match message_ids.get(index) {
+      Some((_, message_id)) => {
+        channel
+          .edit_message(http, *message_id, |message| {
+            message
+              .set_embed(embed.clone())
+              .set_components(components.clone())
+          })
+          .await?;
+      }
+      None => {
+        let message = channel
+          .send_message(http, |message| {
+            message
+              .set_embed(embed.clone())
+              .set_components(components.clone())
+          })
+          .await?;
+        let mut db_instance = INSTANCE.write().unwrap();
+        db_instance.storage_add(NewStorage {
+          datatype: StorageDataType::ProjectDirectory.into(),
+          dataid: Some(message.id.0 as i64),
+          data: "",
+          date: None,
+        });
+      }
+    }
+  }
+  let extra = message_ids.iter().skip(messages.len());
+  for (_, message_id) in extra.clone() {
+    channel.delete_message(http, *message_id).await?;
+  }
+  let mut db_instance = INSTANCE.write().unwrap();
+  db_instance.storage_delete(extra.map(|(id, _)| *id).collect());
+  Ok(())
+}
+
+/// Update the directory after a change of the projects, if it was posted.
+pub async fn refresh_directory(http: &Http) {
+  let _refresh = REFRESH.lock().await;
+  if directory_message_ids().is_empty() {
+    return;
+  }
+  if let Err(e) = update_directory(http).await {
+    error!("Unable to refresh the project directory: {}", e);
+  }
+}
+
+/// Post the directory again at the bottom of the announcement channel.
+pub async fn post_directory(http: &Http) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let _refresh = REFRESH.lock().await;
+  let message_ids = directory_message_ids();
+  for (_, message_id) in &message_ids {
+    // Already deleted by hand
+    let _ = ChannelId(PROJECT_ANOUNCEMENT_CHANNEL)
+      .delete_message(http, *message_id)
+      .await;
+  }
+  {
+    let mut db_instance = INSTANCE.write().unwrap();
+    db_instance.storage_delete(message_ids.iter().map(|(id, _)| *id).collect());
+  }
+  update_directory(http).await
+}
+
+/// Join the projects picked, or leave the ones already joined, and describe what was done.
+async fn toggle_projects(
+  http: &Http,
+  user_id: UserId,
+  values: &[String],
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+  let mut reply = Vec::new();
+  for value in values {
+    let Ok(channel_id) = value.parse::<i64>() else {
+      continue;
+    };
+    let (project, is_member) = {
+      let db_instance = INSTANCE.read().unwrap();
+      let Some((_, project)) = db_instance.projects_search(channel_id, parse::DiscordIds::Channel)
+      else {
+        continue;
+      };
+      let is_member = db_instance
+        .project_members
+        .iter()
+        .any(|member| member.project_id == project.id && member.user_id == user_id.0 as i64);
+      (project.clone(), is_member)
+    };
+    if is_member {
+      leave_project(http, &project, user_id).await?;
+      reply.push(format!("Left <#{}>", channel_id));
+    } else {
+      let outcome = join_project(http, &project, user_id, MemberSource::Directory).await?;
+      reply.push(outcome.describe(channel_id));
+    }
+  }
+  if reply.is_empty() {
+    reply.push(String::from("These projects no longer exist"));
+  }
+  Ok(reply)
+}
+
+/// Join the projects picked in a menu of the directory, or leave the ones already joined.
+///
+/// The answer is deferred first, joining several projects takes longer than Discord waits for it.
+pub async fn handle_directory_select(
+  ctx: &Context,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let is_directory = component
+    .data
+    .custom_id
+    .strip_prefix(MENU_PREFIX)
+    .is_some_and(|index| index.starts_with(':'));
+  if !is_directory {
+    return Ok(());
+  }
+  component
+    .create_interaction_response(&ctx.http, |response| {
+      response
+        .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        .interaction_response_data(|data| data.ephemeral(true))
+    })
+    .await?;
+  let toggled = toggle_projects(&ctx.http, component.user.id, &component.data.values).await;
+  let content = match &toggled {
+    Ok(reply) => reply.join("\n"),
+    Err(_) => String::from("Unable to update your projects, try again later"),
+  };
+  component
+    .edit_original_interaction_response(&ctx.http, |response| response.content(content))
+    .await?;
+  toggled.map(|_| ())
+}
+
+#[test]
+fn test_directory_messages() {
+  let entry = |index: usize| DirectoryEntry {
+    channel_id: ChannelId(index as u64),
+    name: format!("project-{:03}", index),
+    details: "x".repeat(150),
+  };
+  let (embed, components) = &directory_messages(&[])[0];
+  assert_eq!(embed.0["title"], "Project directory");
+  assert!(components.0.is_empty());
+
+  let entries: Vec<DirectoryEntry> = (0..130).map(entry).collect();
+  let messages = directory_messages(&entries);
+  assert_eq!(messages.len(), 2);
+  assert_eq!(messages[0].0 .0["title"], "Project directory (1/2)");
+  assert_eq!(messages[0].1 .0.len(), MENUS_PER_MESSAGE);
+  assert_eq!(messages[1].1 .0.len(), 1);
+  let menu = &messages[1].1 .0[0]["components"][0];
+  assert_eq!(menu["custom_id"], "directory:5");
+  assert_eq!(menu["max_values"], 5);
+  let option = &menu["options"][0];
+  assert_eq!(option["label"], "#project-125");
+  assert_eq!(
+    option["description"].as_str().unwrap().chars().count(),
+    OPTION_TEXT_LENGTH
+  );
+}
@@ -0,0 +1,205 @@
+//! The "Fiche de projet", an embed posted in the announcement channel and pinned in the project
+//! channel, rendered again whenever the project changes.
+use std::error::Error;
+
+use crate::{
+  constants::discordids::PROJECT_ANOUNCEMENT_CHANNEL,
+  core::parse,
+  database::{NewProject, Project, ProjectChangeset, ProjectStatus, INSTANCE},
+  features::categories::CategoryFamily,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use log::error;
+use serenity::{builder::CreateEmbed, http::Http, model::id::ChannelId, utils::Colour};
+
+/// Placeholder stored for the fields left empty at the creation.
+const EMPTY_FIELD: &str = "N/A";
+
+/// A field filled at the creation or edited since.
+fn value(field: Option<&str>) -> Option<&str> {
+  field.filter(|field| !field.is_empty() && *field != EMPTY_FIELD)
+}
+
+impl ProjectStatus {
+  fn colour(self) -> Colour {
+    match self {
+      ProjectStatus::Active => Colour::DARK_GREEN,
+      ProjectStatus::OnHold => Colour::GOLD,
+      ProjectStatus::Archived => Colour::LIGHT_GREY,
+      ProjectStatus::Deleted => Colour::RED,
+    }
+  }
+
+  fn label(self) -> &'static str {
+    match self {
+      ProjectStatus::Active => "Actif",
+      ProjectStatus::OnHold => "En pause",
+      ProjectStatus::Archived => "Archivé",
+      ProjectStatus::Deleted => "Supprimé",
+    }
+  }
+}
+
+pub fn project_fiche(
+  project: &NewProject,
+  created: &DateTime<Tz>,
+  status: ProjectStatus,
+) -> CreateEmbed {
+  let mut embed = CreateEmbed::default();
+  embed
+    .title("Fiche de projet")
+    .url(format!(
+      "https://discord.com/channels/{}/{}",
+      parse::main_guild_id(),
+      project.channel_id
+    ))
+    .description(format!("<#{}>", project.channel_id))
+    .colour(status.colour());
+  for (name, field, inline) in [
+    ("Client", project.client, true),
+    ("Lead projet", project.lead, true),
+    ("Deadline", project.deadline, true),
+    ("Brief projet", project.description, false),
+    ("Contexte projet", project.contexte, false),
+  ] {
+    if let Some(field) = value(field) {
+      embed.field(name, field, inline);
+    }
+  }
+  embed.field("Statut", status.label(), true);
+  let mut footer = format!("Créé le {}", created.format("%d/%m/%Y"));
+  if let Some(codex) = value(project.codex) {
+    footer = format!("{} · {}", codex, footer);
+  }
+  embed.footer(|embed_footer| embed_footer.text(footer));
+  embed
+}
+
+/// Fields of an existing project, to render its fiche.
+pub fn project_fields(project: &Project) -> NewProject<'_> {
+  NewProject {
+    message_id: project.message_id,
+    channel_id: project.channel_id,
+    pinned_message_id: project.pinned_message_id,
+    codex: Some(&project.codex),
+    client: Some(&project.client),
+    lead: Some(&project.lead),
+    deadline: Some(&project.deadline),
+    description: Some(&project.description),
+    contexte: Some(&project.contexte),
+    deadline_date: project.deadline_date,
+  }
+}
+
+/// Edit the announcement and the pinned copy of the fiche to match the project.
+///
+/// The text of the fiches posted before the embeds is removed.
+pub async fn update_fiche(
+  http: &Http,
+  project: &Project,
+  timezone: Tz,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let created = DateTime::<Utc>::from(project.created_at).with_timezone(&timezone);
+  let fiche = project_fiche(&project_fields(project), &created, project.status());
+  ChannelId(PROJECT_ANOUNCEMENT_CHANNEL)
+    .edit_message(http, project.message_id as u64, |message| {
+      message.content("").set_embed(fiche.clone())
+    })
+    .await?;
+  if let Some(pinned_message_id) = project.pinned_message_id {
+    ChannelId(project.channel_id as u64)
+      .edit_message(http, pinned_message_id as u64, |message| {
+        message.content("").set_embed(fiche)
+      })
+      .await?;
+  }
+  Ok(())
+}
+
+/// Mark the projects created before their status was stored as archived when their channel is in
+/// the archive category.
+async fn migrate_status(
+  http: &Http,
+  project: &Project,
+) -> Result<Project, Box<dyn Error + Send + Sync>> {
+  if project.status() != ProjectStatus::Active {
+    return Ok(project.clone());
+  }
+  let channel = ChannelId(project.channel_id as u64)
+    .to_channel(http)
+    .await?
+    .guild()
+    .ok_or("the project channel is not a guild channel")?;
+  if !channel
+    .parent_id
+    .is_some_and(|category| CategoryFamily::Archives.contains(category))
+  {
+    return Ok(project.clone());
+  }
+  let mut db_instance = INSTANCE.write().unwrap();
+  let status = ProjectStatus::Archived.to_string();
+  let updated = db_instance.project_update(
+    project.id,
+    ProjectChangeset {
+      status: Some(&status),
+      ..Default::default()
+    },
+  )?;
+  Ok(updated.clone())
+}
+
+/// Render the fiches of all the projects as embeds, after storing the status of the archived
+/// ones, returns how many were updated.
+pub async fn migrate_fiches(http: &Http, timezone: Tz) -> (usize, usize) {
+  let projects = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance.projects.clone()
+  };
+  let mut updated = 0;
+  for project in &projects {
+    let result = match migrate_status(http, project).await {
+      Ok(project) => update_fiche(http, &project, timezone).await,
+      Err(e) => Err(e),
+    };
+    match result {
+      Ok(()) => updated += 1,
+      Err(e) => error!(
+        "Unable to migrate the fiche of project {}: {}",
+        project.id, e
+      ),
+    }
+  }
+  (updated, projects.len())
+}
+
+#[test]
+fn test_project_fiche() {
+  use chrono::TimeZone;
+
+  let project = NewProject {
+    message_id: 1,
+    channel_id: 2,
+    codex: Some("ACME-01"),
+    client: Some("Acme"),
+    lead: Some("Alice"),
+    deadline: Some(EMPTY_FIELD),
+    description: Some("Website"),
+    contexte: Some(""),
+    pinned_message_id: None,
+    deadline_date: None,
+  };
+  let created = chrono_tz::Europe::Paris
+    .with_ymd_and_hms(2026, 10, 19, 10, 0, 0)
+    .unwrap();
+  let fiche = project_fiche(&project, &created, ProjectStatus::Archived);
+  let names: Vec<&str> = fiche.0["fields"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|field| field["name"].as_str().unwrap())
+    .collect();
+  assert_eq!(names, ["Client", "Lead projet", "Brief projet", "Statut"]);
+  assert_eq!(fiche.0["footer"]["text"], "ACME-01 · Créé le 19/10/2026");
+  assert_eq!(fiche.0["color"], Colour::LIGHT_GREY.0);
+}
@@ -0,0 +1,387 @@
+//! Paginated list of the projects, filtered by client, lead, codex or status.
+//!
+//! The filters are written in the footer of the embed so the page buttons can rebuild the list.
+use std::{collections::BTreeSet, error::Error, fmt::Display, str::FromStr};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    parse,
+  },
+  database::{Project, ProjectStatus, INSTANCE},
+};
+use procedural_macros::command;
+use serenity::{
+  builder::{CreateComponents, CreateEmbed},
+  http::Http,
+  model::{
+    application::{
+      component::ButtonStyle,
+      interaction::{
+        application_command::ApplicationCommandInteraction, autocomplete::AutocompleteInteraction,
+        message_component::MessageComponentInteraction, InteractionResponseType,
+      },
+    },
+    channel::{GuildChannel, PermissionOverwriteType},
+    id::{ChannelId, GuildId},
+    Permissions,
+  },
+  prelude::*,
+};
+
+/// Prefix of the custom id of the page buttons, followed by the page.
+const BUTTON_PREFIX: &str = "projects";
+const PAGE_SIZE: usize = 10;
+/// Separates the parts of the footer, the last one being the filters.
+const FOOTER_SEPARATOR: &str = " · ";
+/// Discord shows at most 25 autocomplete choices.
+const MAX_CHOICES: usize = 25;
+const PROJECTS_USAGE: &str = "Usage: @BOT projects [client=<CLIENT>] [lead=<LEAD>] [codex=<CODEX>] [active|on_hold|archived|deleted]";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ProjectFilter {
+  client: Option<String>,
+  lead: Option<String>,
+  codex: Option<String>,
+  status: Option<ProjectStatus>,
+}
+
+fn contains_ignore_case(value: &str, search: &Option<String>) -> bool {
+  search
+    .as_ref()
+    .is_none_or(|search| value.to_lowercase().contains(&search.to_lowercase()))
+}
+
+impl ProjectFilter {
+  fn parse(args: &[String]) -> Result<Self, String> {
+    let mut filter = ProjectFilter::default();
+    for arg in args {
+      match arg.split_once('=') {
+        Some(("client", value)) => filter.client = Some(value.to_string()),
+        Some(("lead", value)) => filter.lead = Some(value.to_string()),
+        Some(("codex", value)) => filter.codex = Some(value.to_string()),
+        None => match ProjectStatus::from_str(arg) {
+          Ok(status) => filter.status = Some(status),
+          Err(_) => return Err(format!("Invalid filter: {}\n{}", arg, PROJECTS_USAGE)),
+        },
+        _ => return Err(format!("Invalid filter: {}\n{}", arg, PROJECTS_USAGE)),
+      }
+    }
+    Ok(filter)
+  }
+
+  /// The deleted projects are only listed when asked for.
+  fn matches(&self, project: &Project) -> bool {
+    let status = project.status();
+    contains_ignore_case(&project.client, &self.client)
+      && contains_ignore_case(&project.lead, &self.lead)
+      && contains_ignore_case(&project.codex, &self.codex)
+      && self
+        .status
+        .map_or(status != ProjectStatus::Deleted, |filter| filter == status)
+  }
+}
+
+/// The filters as written in a command, parsed back by [ProjectFilter::parse].
+impl Display for ProjectFilter {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let mut filters = Vec::new();
+    for (name, value) in [
+      ("client", &self.client),
+      ("lead", &self.lead),
+      ("codex", &self.codex),
+    ] {
+      match value {
+        Some(value) if value.contains(' ') => filters.push(format!("{}=\"{}\"", name, value)),
+        Some(value) => filters.push(format!("{}={}", name, value)),
+        None => (),
+      }
+    }
+    if let Some(status) = self.status {
+      filters.push(status.to_string());
+    }
+    write!(f, "{}", filters.join(" "))
+  }
+}
+
+struct ProjectEntry {
+  project: Project,
+  channel: GuildChannel,
+}
+
+/// Projects of the guild matching the filter, sorted by channel name.
+async fn find_projects(
+  http: &Http,
+  guild_id: GuildId,
+  filter: &ProjectFilter,
+) -> Result<Vec<ProjectEntry>, Box<dyn Error + Send + Sync>> {
+  let channels = guild_id.channels(http).await?;
+  let projects = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance.projects.clone()
+  };
+  let mut entries: Vec<ProjectEntry> = projects
+    .into_iter()
+    .filter_map(|project| {
+      let channel = channels.get(&ChannelId(project.channel_id as u64))?;
+      filter.matches(&project).then(|| ProjectEntry {
+        project,
+        channel: channel.clone(),
+      })
+    })
+    .collect();
+  entries.sort_by(|a, b| a.channel.name.cmp(&b.channel.name));
+  Ok(entries)
+}
+
+/// Members added to the project channel, see [crate::core::permissions::member_channel_read].
+fn member_count(channel: &GuildChannel) -> usize {
+  channel
+    .permission_overwrites
+    .iter()
+    .filter(|overwrite| {
+      matches!(overwrite.kind, PermissionOverwriteType::Member(_))
+        && overwrite.allow.contains(Permissions::VIEW_CHANNEL)
+    })
+    .count()
+}
+
+fn describe_entry(entry: &ProjectEntry) -> String {
+  let project = &entry.project;
+  let mut description = format!(
+    "<#{}> · Client: {} · Lead: {}\nDeadline: {} · {} members",
+    project.channel_id,
+    project.client,
+    project.lead,
+    project.deadline,
+    member_count(&entry.channel)
+  );
+  // The id of a message holds its creation date
+  if let Some(last_message) = entry.channel.last_message_id {
+    description.push_str(&format!(
+      " · last activity <t:{}:R>",
+      last_message.created_at().unix_timestamp()
+    ));
+  }
+  match project.status() {
+    ProjectStatus::Active => (),
+    ProjectStatus::OnHold => description.push_str(" · on hold"),
+    ProjectStatus::Archived => description.push_str(" · archived"),
+    ProjectStatus::Deleted => description.push_str(" · deleted"),
+  }
+  description
+}
+
+fn projects_page(
+  entries: &[ProjectEntry],
+  filter: &ProjectFilter,
+  page: usize,
+) -> (CreateEmbed, CreateComponents) {
+  let pages = entries.len().div_ceil(PAGE_SIZE).max(1);
+  let page = page.min(pages - 1);
+
+  let mut embed = CreateEmbed::default();
+  embed.title("Projects");
+  if entries.is_empty() {
+    embed.description("No project found");
+  }
+  for entry in entries.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+    let name = match entry.project.codex.as_str() {
+      "" | "#PXXX" => format!("#{}", entry.channel.name),
+      codex => format!("#{} ({})", entry.channel.name, codex),
+    };
+    embed.field(name, describe_entry(entry), false);
+  }
+  let mut footer = format!(
+    "Page {}/{}{}{} projects",
+    page + 1,
+    pages,
+    FOOTER_SEPARATOR,
+    entries.len()
+  );
+  let filter = filter.to_string();
+  if !filter.is_empty() {
+    footer.push_str(FOOTER_SEPARATOR);
+    footer.push_str(&filter);
+  }
+  embed.footer(|embed_footer| embed_footer.text(footer));
+
+  let mut components = CreateComponents::default();
+  if pages > 1 {
+    components.create_action_row(|row| {
+      row
+        .create_button(|button| {
+          button
+            .custom_id(format!("{}:{}", BUTTON_PREFIX, page.saturating_sub(1)))
+            .label("Previous")
+            .style(ButtonStyle::Secondary)
+            .disabled(page == 0)
+        })
+        .create_button(|button| {
+          button
+            .custom_id(format!("{}:{}", BUTTON_PREFIX, page + 1))
+            .label("Next")
+            .style(ButtonStyle::Secondary)
+            .disabled(page + 1 == pages)
+        })
+    });
+  }
+  (embed, components)
+}
+
+#[command]
+pub async fn projects(params: CallBackParams) -> CallbackReturn {
+  let filter = match ProjectFilter::parse(&params.args[1..]) {
+    Ok(filter) => filter,
+    Err(error) => return Ok(Some(error)),
+  };
+  let http = &params.context.http;
+  let guild_id = params.message.guild_id.unwrap_or_else(parse::main_guild_id);
+  let entries = find_projects(http, guild_id, &filter).await?;
+  let (embed, components) = projects_page(&entries, &filter, 0);
+  params
+    .message
+    .channel_id
+    .send_message(http, |message| {
+      message.set_embed(embed).set_components(components)
+    })
+    .await?;
+  Ok(None)
+}
+
+/// The `/projects` slash command, its options are the filters of the `projects` command.
+pub async fn projects_slash_command(
+  ctx: &Context,
+  command: &ApplicationCommandInteraction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let args: Vec<String> = command
+    .data
+    .options
+    .iter()
+    .filter_map(|option| {
+      let value = option.value.as_ref()?.as_str()?;
+      Some(match option.name.as_str() {
+        "status" => value.to_string(),
+        name => format!("{}={}", name, value),
+      })
+    })
+    .collect();
+  let filter = ProjectFilter::parse(&args);
+  let entries = match &filter {
+    Ok(filter) => {
+      let guild_id = command.guild_id.unwrap_or_else(parse::main_guild_id);
+      find_projects(&ctx.http, guild_id, filter).await?
+    }
+    Err(_) => Vec::new(),
+  };
+  command
+    .create_interaction_response(&ctx.http, |response| {
+      response
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|data| match &filter {
+          Ok(filter) => {
+            let (embed, components) = projects_page(&entries, filter, 0);
+            data.set_embed(embed).set_components(components)
+          }
+          Err(error) => data.content(error).ephemeral(true),
+        })
+    })
+    .await?;
+  Ok(())
+}
+
+/// Suggest the clients or leads of the existing projects while typing the filter.
+pub async fn projects_autocomplete(
+  ctx: &Context,
+  autocomplete: &AutocompleteInteraction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let Some(option) = autocomplete
+    .data
+    .options
+    .iter()
+    .find(|option| option.focused)
+  else {
+    return Ok(());
+  };
+  let typed = option
+    .value
+    .as_ref()
+    .and_then(|value| value.as_str())
+    .unwrap_or_default()
+    .to_lowercase();
+  let suggestions: BTreeSet<String> = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .projects
+      .iter()
+      .filter_map(|project| match option.name.as_str() {
+        "client" => Some(&project.client),
+        "lead" => Some(&project.lead),
+        _ => None,
+      })
+      .filter(|value| !value.is_empty() && *value != "N/A" && value.to_lowercase().contains(&typed))
+      .cloned()
+      .collect()
+  };
+  autocomplete
+    .create_autocomplete_response(&ctx.http, |response| {
+      for suggestion in suggestions.iter().take(MAX_CHOICES) {
+        response.add_string_choice(suggestion, suggestion);
+      }
+      response
+    })
+    .await?;
+  Ok(())
+}
+
+/// Handle a click on the page buttons of the list.
+pub async fn handle_projects_button(
+  ctx: &Context,
+  component: &MessageComponentInteraction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let Some(page) = component
+    .data
+    .custom_id
+    .strip_prefix(BUTTON_PREFIX)
+    .and_then(|page| page.strip_prefix(':'))
+    .and_then(|page| page.parse().ok())
+  else {
+    return Ok(());
+  };
+  let filters = component
+    .message
+    .embeds
+    .first()
+    .and_then(|embed| embed.footer.as_ref())
+    .and_then(|footer| footer.text.splitn(3, FOOTER_SEPARATOR).nth(2))
+    .unwrap_or_default();
+  let filter = ProjectFilter::parse(&parse::split_message_args(filters))?;
+  let guild_id = component.guild_id.unwrap_or_else(parse::main_guild_id);
+  let entries = find_projects(&ctx.http, guild_id, &filter).await?;
+  let (embed, components) = projects_page(&entries, &filter, page);
+  component
+    .create_interaction_response(&ctx.http, |response| {
+      response
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|data| data.set_embed(embed).set_components(components))
+    })
+    .await?;
+  Ok(())
+}
+
+#[test]
+fn test_project_filter() {
+  let args = parse::split_message_args(r#"client="Big Co" lead=bob archived"#);
+  let filter = ProjectFilter::parse(&args).unwrap();
+  assert_eq!(filter.client.as_deref(), Some("Big Co"));
+  assert_eq!(filter.status, Some(ProjectStatus::Archived));
+  assert_eq!(
+    ProjectFilter::parse(&parse::split_message_args(&filter.to_string())),
+    Ok(filter)
+  );
+  assert!(ProjectFilter::parse(&[String::from("owner=bob")]).is_err());
+  assert_eq!(
+    ProjectFilter::parse(&[String::from("on_hold")]).map(|filter| filter.status),
+    Ok(Some(ProjectStatus::OnHold))
+  );
+}
@@ -0,0 +1,268 @@
+//! Members of the projects, recorded when they are given access to the channel.
+//!
+//! The permission overwrites of the channels stay the reference: a daily reconciliation reports
+//! the members added by hand in discord and the ones whose access was removed, and
+//! `project members sync` records them. The members of a project found before any was recorded
+//! are marked as imported, the users erased with `forget` are never recorded again.
+use std::{collections::BTreeSet, error::Error, fmt::Write};
+
+use super::find_project;
+use crate::{
+  constants::discordids::DEVOPS_CHANNEL,
+  core::{
+    commands::CallBackParams,
+    date_parse, parse, permissions,
+    scheduler::{self, Job},
+  },
+  database::{
+    MemberSource, NewProjectMember, Project, ProjectStatus, Role, StorageDataType, INSTANCE,
+  },
+};
+use chrono::{Duration, Utc};
+use log::error;
+use serenity::{
+  http::Http,
+  model::{
+    channel::{GuildChannel, PermissionOverwriteType},
+    id::{ChannelId, UserId},
+    Permissions,
+  },
+};
+
+const MEMBERS_USAGE: &str = "Usage: @BOT project members [#channel] | check | sync";
+/// Hours between two reconciliations.
+const RECONCILIATION_HOURS: i64 = 24;
+/// Users listed per line of the differences.
+const MENTIONS_PER_LINE: usize = 40;
+const SYNC_HINT: &str = "`project members sync` to update the members from the channels";
+
+/// Record that a user was given access to the channel, if it is a project.
+pub fn record_join(channel_id: ChannelId, user_id: UserId, source: MemberSource) {
+  let mut db_instance = INSTANCE.write().unwrap();
+  let Some((_, project)) =
+    db_instance.projects_search(channel_id.0 as i64, parse::DiscordIds::Channel)
+  else {
+    return;
+  };
+  let new_member = NewProjectMember {
+    project_id: project.id,
+    user_id: user_id.0 as i64,
+    source: &source.to_string(),
+  };
+  if let Err(e) = db_instance.project_member_add(new_member) {
+    error!(
+      "Unable to record the member {} of {}: {}",
+      user_id, channel_id, e
+    );
+  }
+}
+
+/// Record that a user lost access to the channel, if it is a project.
+pub fn record_leave(channel_id: ChannelId, user_id: UserId) {
+  let mut db_instance = INSTANCE.write().unwrap();
+  let Some((_, project)) =
+    db_instance.projects_search(channel_id.0 as i64, parse::DiscordIds::Channel)
+  else {
+    return;
+  };
+  let project_id = project.id;
+  if let Err(e) = db_instance.project_member_remove(project_id, user_id.0 as i64) {
+    error!(
+      "Unable to remove the member {} of {}: {}",
+      user_id, channel_id, e
+    );
+  }
+}
+
+/// Users allowed to read the channel by a member overwrite.
+fn channel_members(channel: &GuildChannel) -> BTreeSet<i64> {
+  channel
+    .permission_overwrites
+    .iter()
+    .filter(|overwrite| overwrite.allow.contains(Permissions::VIEW_CHANNEL))
+    .filter_map(|overwrite| match overwrite.kind {
+      PermissionOverwriteType::Member(user_id) => Some(user_id.0 as i64),
+      _ => None,
+    })
+    .collect()
+}
+
+/// Difference between the recorded members of a project and the overwrites of its channel.
+struct Drift {
+  project: Project,
+  /// Allowed in the channel but not recorded
+  unrecorded: Vec<i64>,
+  /// Recorded but no longer allowed in the channel
+  stale: Vec<i64>,
+  /// No member was recorded yet, the project predates the members
+  first_sync: bool,
+}
+
+impl Drift {
+  /// One line per kind of difference, long lists of users are cut over several lines to fit in
+  /// the messages.
+  fn describe(&self) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (label, users) in [
+      ("not recorded", &self.unrecorded),
+      ("without access", &self.stale),
+    ] {
+      for users in users.chunks(MENTIONS_PER_LINE) {
+        let mentions: Vec<String> = users.iter().map(|user| format!("<@{}>", user)).collect();
+        lines.push(format!(
+          "<#{}>: {} {}",
+          self.project.channel_id,
+          label,
+          mentions.join(", ")
+        ));
+      }
+    }
+    lines
+  }
+}
+
+async fn find_drifts(http: &Http) -> Result<Vec<Drift>, Box<dyn Error + Send + Sync>> {
+  let channels = parse::main_guild_id().channels(http).await?;
+  let db_instance = INSTANCE.read().unwrap();
+  let forgotten: BTreeSet<i64> = db_instance
+    .filter_storage_type(StorageDataType::Forgotten)
+    .iter()
+    .filter_map(|stored| stored.dataid)
+    .collect();
+  let drifts = db_instance
+    .projects
+    .iter()
+    .filter(|project| project.status() != ProjectStatus::Deleted)
+    .filter_map(|project| {
+      let channel = channels.get(&ChannelId(project.channel_id as u64))?;
+      let allowed = channel_members(channel);
+      let recorded: BTreeSet<i64> = db_instance
+        .project_members
+        .iter()
+        .filter(|member| member.project_id == project.id)
+        .map(|member| member.user_id)
+        .collect();
+      let drift = Drift {
+        project: project.clone(),
+        unrecorded: allowed
+          .difference(&recorded)
+          .filter(|user| !forgotten.contains(user))
+          .copied()
+          .collect(),
+        stale: recorded.difference(&allowed).copied().collect(),
+        first_sync: recorded.is_empty(),
+      };
+      (!drift.unrecorded.is_empty() || !drift.stale.is_empty()).then_some(drift)
+    })
+    .collect();
+  Ok(drifts)
+}
+
+/// Update the recorded members to match the channels.
+fn fix_drifts(drifts: &[Drift]) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut db_instance = INSTANCE.write().unwrap();
+  for drift in drifts {
+    let source = if drift.first_sync {
+      MemberSource::Imported
+    } else {
+      MemberSource::Discord
+    }
+    .to_string();
+    for user_id in &drift.unrecorded {
+      db_instance.project_member_add(NewProjectMember {
+        project_id: drift.project.id,
+        user_id: *user_id,
+        source: &source,
+      })?;
+    }
+    for user_id in &drift.stale {
+      db_instance.project_member_remove(drift.project.id, *user_id)?;
+    }
+  }
+  Ok(())
+}
+
+/// Report the differences between the recorded members and the channels to the admins, then run
+/// again the next day.
+pub async fn reconciliation_job(http: &Http) -> Result<(), Box<dyn Error + Send + Sync>> {
+  scheduler::schedule(
+    Utc::now() + Duration::hours(RECONCILIATION_HOURS),
+    Job::MemberReconciliation,
+  );
+  let drifts = find_drifts(http).await?;
+  if drifts.is_empty() {
+    return Ok(());
+  }
+  let mut report = String::from("Project members differing from the channels:\n");
+  for line in drifts.iter().flat_map(Drift::describe) {
+    writeln!(report, "{}", line).expect("unable to append string");
+  }
+  report.push_str(SYNC_HINT);
+  for chunk in parse::split_reply(&report) {
+    ChannelId(DEVOPS_CHANNEL)
+      .send_message(http, |message| {
+        message
+          .content(chunk)
+          .allowed_mentions(|mentions| mentions.empty_parse())
+      })
+      .await?;
+  }
+  Ok(())
+}
+
+fn list_members(params: &CallBackParams<'_>) -> String {
+  let project = match find_project(params.message, &params.args[2..]) {
+    Ok((project, [])) => project,
+    Ok(_) => return String::from(MEMBERS_USAGE),
+    Err(error) => return error,
+  };
+  let timezone = date_parse::user_timezone(params.message.author.id.0);
+  let db_instance = INSTANCE.read().unwrap();
+  let mut members: Vec<_> = db_instance
+    .project_members
+    .iter()
+    .filter(|member| member.project_id == project.id)
+    .collect();
+  if members.is_empty() {
+    return format!("<#{}> has no member", project.channel_id);
+  }
+  members.sort_by_key(|member| member.joined_at);
+  let mut reply = format!("Members of <#{}>:\n", project.channel_id);
+  for member in members {
+    writeln!(
+      reply,
+      "<@{}> since {} ({})",
+      member.user_id,
+      date_parse::format_user_date_time(member.joined_at.and_utc(), timezone),
+      member.source
+    )
+    .expect("unable to append string");
+  }
+  reply
+}
+
+pub async fn members(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let action = params.args.get(2).map(String::as_str);
+  if !matches!(action, Some("check" | "sync")) {
+    return Ok(list_members(params));
+  }
+  if permissions::user_role(params.message.author.id) < Role::Admin {
+    return Ok(String::from("Only admins can reconcile the members"));
+  }
+  let drifts = find_drifts(&params.context.http).await?;
+  if drifts.is_empty() {
+    return Ok(String::from("The members match the channels"));
+  }
+  // Split in messages between the lines by the command handler
+  let mut reply = String::new();
+  for line in drifts.iter().flat_map(Drift::describe) {
+    writeln!(reply, "{}", line).expect("unable to append string");
+  }
+  if action == Some("sync") {
+    fix_drifts(&drifts)?;
+    reply.push_str("Members updated from the channels");
+  } else {
+    reply.push_str(SYNC_HINT);
+  }
+  Ok(reply)
+}
@@ -0,0 +1,821 @@
+mod access;
+mod deadlines;
+mod directory;
+mod fiche;
+mod listing;
+mod members;
+mod templates;
+
+pub use self::access::handle_access_button;
+use self::access::{join_project, leave_project, JoinOutcome};
+pub use self::deadlines::{
+  deadlines, next_summary_date, reminder_job, schedule_all_reminders, set_deadline_date,
+  summary_job,
+};
+use self::deadlines::{parse_deadline, schedule_reminders};
+use self::directory::post_directory;
+pub use self::directory::{handle_directory_select, refresh_directory};
+use self::fiche::{migrate_fiches, project_fiche, update_fiche};
+pub use self::listing::{
+  handle_projects_button, projects, projects_autocomplete, projects_slash_command,
+};
+pub use self::members::reconciliation_job;
+use self::members::{record_join, record_leave};
+use self::templates::{apply_template, find_template, project_channel_ids, ProjectTemplate};
+
+use std::{
+  collections::HashMap,
+  error::Error,
+  fmt::{Display, Write},
+  sync::Arc,
+  time::SystemTime,
+};
+
+use crate::constants::discordids::PROJECT_ANOUNCEMENT_CHANNEL;
+use crate::core::{
+  commands::{CallBackParams, CallbackReturn},
+  date_parse,
+  parse::{self, discord_str_to_id},
+  permissions::{self, member_channel_read, ReadState},
+  scheduler::{self, Job},
+};
+use crate::{
+  core::parse::DiscordIds,
+  database::{
+    MemberSource, NewProject, NewProjectChange, Project, ProjectChangeset, ProjectStatus, Role,
+    INSTANCE,
+  },
+  features::categories::{create_in_family, move_to_family, CategoryFamily},
+};
+use chrono::{DateTime, Duration, Utc};
+use futures::FutureExt;
+use log::error;
+use procedural_macros::command;
+use serenity::{
+  http::{Http, StatusCode},
+  model::{
+    channel::{Channel, ChannelType, GuildChannel, Message, PermissionOverwriteType, Reaction},
+    guild::Guild,
+    id::{ChannelId, UserId},
+    user::User,
+  },
+  prelude::*,
+  Error as SerenityError,
+};
+
+const PROJECT_USAGE: &str = "Usage: @BOT project edit [#channel] <field>=<value>... | history [#channel] | archive|hold|restore [#channel] | members [#channel]|check|sync | access [#channel] [open|approval|closed] | template list|show|set|remove | directory | migrate-fiches";
+/// Days a deleted project can be restored before its channel is removed.
+const DELETION_GRACE_DAYS: i64 = 7;
+
+const ARGUMENT_LIST: [&str; 6] = [
+  "codex",
+  "client",
+  "lead",
+  "deadline",
+  "description",
+  "contexte",
+];
+
+pub fn project_creation_args(args: &'_ [String]) -> Result<HashMap<&'_ str, &'_ str>, String> {
+  let mut project_args: HashMap<&str, &str> = HashMap::new();
+  for arg in args {
+    let find = arg.find('=');
+    if let Some(index) = find {
+      let left = &arg[..index];
+      if ARGUMENT_LIST.contains(&left) || left == "template" {
+        let right = &arg[index + 1..];
+        project_args.insert(left, right);
+      } else {
+        return Err(format!("Invalid argument {}", arg));
+      }
+    } else {
+      if project_args.contains_key("name") {
+        return Err(format!("Unexpected argument {}", arg));
+      }
+      project_args.insert("name", arg);
+    }
+  }
+  if project_args.contains_key("name") {
+    return Ok(project_args);
+  }
+  Err(String::from("Missing name."))
+}
+
+/// Template asked with `template=<name>`, checked before anything is created.
+fn requested_template(
+  project_args: &HashMap<&str, &str>,
+) -> Result<Option<ProjectTemplate>, String> {
+  match project_args.get("template") {
+    Some(name) => match find_template(name) {
+      Some(template) => Ok(Some(template)),
+      None => Err(format!("Unknown template: {}", name)),
+    },
+    None => Ok(None),
+  }
+}
+
+fn project_init<'fut>(
+  project_args: HashMap<&'fut str, &'fut str>,
+  template: Option<ProjectTemplate>,
+  project_chan: ChannelId,
+  message: &'fut Message,
+  http: &'fut Arc<Http>,
+) -> CallbackReturn<'fut> {
+  async move {
+    let datetime = date_parse::user_now(message.author.id.0);
+
+    let overwrite = member_channel_read(message.author.id, ReadState::Allow);
+    project_chan.create_permission(http, &overwrite).await?;
+
+    let client = project_args.get("client").unwrap_or(&"");
+    let codex = project_args.get("codex").unwrap_or(&"#PXXX");
+    let author_name = &*message.author.name;
+    let lead = project_args.get("lead").unwrap_or(&author_name);
+    let deadline = project_args.get("deadline").unwrap_or(&"N/A");
+    let description = project_args.get("description").unwrap_or(&"N/A");
+    let contexte = project_args.get("contexte").unwrap_or(&"N/A");
+    let mut new_project = NewProject {
+      message_id: 0,
+      channel_id: project_chan.0 as i64,
+      pinned_message_id: None,
+      codex: Some(codex),
+      client: Some(client),
+      lead: Some(lead),
+      deadline: Some(deadline),
+      description: Some(description),
+      contexte: Some(contexte),
+      deadline_date: parse_deadline(deadline, datetime.date_naive()),
+    };
+    let fiche = project_fiche(&new_project, &datetime, ProjectStatus::Active);
+    let annoucement_message = ChannelId(PROJECT_ANOUNCEMENT_CHANNEL)
+      .send_message(http, |message| message.set_embed(fiche.clone()))
+      .await?;
+    let channel_message = project_chan
+      .send_message(http, |message| message.set_embed(fiche))
+      .await?;
+    channel_message.pin(http).await?;
+    new_project.message_id = annoucement_message.id.0 as i64;
+    new_project.pinned_message_id = Some(channel_message.id.0 as i64);
+    let project = {
+      let mut db_instance = INSTANCE.write().unwrap();
+      db_instance.project_add(new_project).clone()
+    };
+    schedule_reminders(&project);
+    record_join(project_chan, message.author.id, MemberSource::Creator);
+    if let Some(template) = template {
+      apply_template(
+        http,
+        &project,
+        project_args["name"],
+        &template,
+        message.author.id,
+      )
+      .await?;
+    }
+    refresh_directory(http).await;
+    annoucement_message.react(http, '✅').await?;
+    if message.channel_id == ChannelId(PROJECT_ANOUNCEMENT_CHANNEL) {
+      message.delete(http).await?;
+      return Ok(None);
+    }
+    Ok(Some(String::from(":ok:")))
+  }
+  .boxed()
+}
+
+#[command]
+pub async fn create(params: CallBackParams) -> CallbackReturn {
+  let project_args = match project_creation_args(&params.args[1..]) {
+    Ok(result) => result,
+    Err(error) => return Ok(Some(error)),
+  };
+  let template = match requested_template(&project_args) {
+    Ok(template) => template,
+    Err(error) => return Ok(Some(error)),
+  };
+  let http = &params.context.http;
+  let newchan = create_in_family(http, CategoryFamily::Projects, |channel| {
+    channel.kind(ChannelType::Text).name(project_args["name"])
+  })
+  .await?;
+
+  project_init(
+    project_args,
+    template,
+    newchan.id,
+    params.message,
+    &params.context.http,
+  )
+  .await
+}
+
+#[command]
+pub async fn add(params: CallBackParams<'_>) -> CallbackReturn {
+  let (project_chan_id, _) =
+    parse::discord_str_to_id(&params.args[1], Some(parse::DiscordIds::Channel))?;
+  let project_chan = ChannelId(project_chan_id);
+  let project_args = match project_creation_args(&params.args[2..]) {
+    Ok(result) => result,
+    Err(error) => return Ok(Some(error)),
+  };
+  let template = match requested_template(&project_args) {
+    Ok(template) => template,
+    Err(error) => return Ok(Some(error)),
+  };
+
+  project_init(
+    project_args,
+    template,
+    project_chan,
+    params.message,
+    &params.context.http,
+  )
+  .await
+}
+
+#[command]
+pub async fn delete(params: CallBackParams) -> CallbackReturn {
+  let (target, _) = match parse::discord_str_to_id(&params.args[1], Some(DiscordIds::Channel)) {
+    Ok(target) => target,
+    Err(error) => return Ok(Some(error)),
+  };
+  let project = {
+    let db_instance = INSTANCE.read().unwrap();
+    match db_instance.projects_search(target as i64, DiscordIds::Channel) {
+      Some((_, project)) => project.clone(),
+      None => return Ok(Some(String::from("Channel wasn't found"))),
+    }
+  };
+  if project.status() == ProjectStatus::Deleted {
+    return Ok(Some(format!("<#{}> is already deleted", target)));
+  }
+  let author = params.message.author.id;
+  let project = set_project_status(
+    &params.context.http,
+    &project,
+    ProjectStatus::Deleted,
+    author,
+  )
+  .await?;
+  let deletion = deletion_date(&project).expect("a deleted project has a deletion date");
+  Ok(Some(format!(
+    "<#{}> will be deleted on {}, `project restore` to cancel",
+    target,
+    date_parse::format_user_date_time(deletion, date_parse::user_timezone(author.0))
+  )))
+}
+
+/// When a deleted project is removed for good.
+pub fn deletion_date(project: &Project) -> Option<DateTime<Utc>> {
+  let deleted_at = project
+    .deleted_at
+    .filter(|_| project.status() == ProjectStatus::Deleted)?;
+  Some(DateTime::<Utc>::from(deleted_at) + Duration::days(DELETION_GRACE_DAYS))
+}
+
+/// A channel or message that is already gone.
+fn is_not_found(error: &SerenityError) -> bool {
+  matches!(error, SerenityError::Http(error) if error.status_code() == Some(StatusCode::NOT_FOUND))
+}
+
+/// Delete the channels and the announcement of a project, skipping the ones already gone.
+async fn delete_project_messages(
+  http: &Http,
+  project: &Project,
+  linked: &[i64],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  for channel_id in std::iter::once(project.channel_id).chain(linked.iter().copied()) {
+    match ChannelId(channel_id as u64).delete(http).await {
+      Err(error) if !is_not_found(&error) => return Err(error.into()),
+      _ => (),
+    }
+  }
+  match ChannelId(PROJECT_ANOUNCEMENT_CHANNEL)
+    .delete_message(http, project.message_id as u64)
+    .await
+  {
+    Err(error) if !is_not_found(&error) => Err(error.into()),
+    _ => Ok(()),
+  }
+}
+
+/// Remove a deleted project, its channels and its announcement once the grace period is over.
+///
+/// The project is only removed from the database once its channels are gone, the job is retried
+/// until then.
+pub async fn deletion_job(
+  http: &Http,
+  project_id: i32,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let (project, linked) = {
+    let db_instance = INSTANCE.read().unwrap();
+    // Restored since, or deleted again later with its own job
+    let project = db_instance
+      .projects
+      .iter()
+      .find(|project| project.id == project_id)
+      .filter(|project| deletion_date(project).is_some_and(|deletion| deletion <= Utc::now()))
+      .cloned();
+    let Some(project) = project else {
+      return Ok(());
+    };
+    let linked: Vec<i64> = db_instance
+      .project_channels
+      .iter()
+      .filter(|channel| channel.project_id == project_id)
+      .map(|channel| channel.channel_id)
+      .collect();
+    (project, linked)
+  };
+  if let Err(error) = delete_project_messages(http, &project, &linked).await {
+    scheduler::retry(Job::ProjectDeletion(project_id));
+    return Err(error);
+  }
+  {
+    let mut db_instance = INSTANCE.write().unwrap();
+    db_instance.projects_delete(project.channel_id as u64)?;
+  }
+  refresh_directory(http).await;
+  Ok(())
+}
+
+/// Change the status of a project, moving its channels in or out of the archive category, then
+/// record the change in its history and render its fiche again.
+pub async fn set_project_status(
+  http: &Http,
+  project: &Project,
+  status: ProjectStatus,
+  author: UserId,
+) -> Result<Project, Box<dyn Error + Send + Sync>> {
+  let channel = ChannelId(project.channel_id as u64)
+    .to_channel(http)
+    .await?
+    .guild()
+    .ok_or("the project channel is not a guild channel")?;
+  let archived = channel
+    .parent_id
+    .is_some_and(|category| CategoryFamily::Archives.contains(category));
+  let family = match status {
+    ProjectStatus::Archived if !archived => Some(CategoryFamily::Archives),
+    ProjectStatus::Active | ProjectStatus::OnHold if archived => Some(CategoryFamily::Projects),
+    _ => None,
+  };
+  if let Some(family) = family {
+    // Each channel fills the category, the next one may overflow
+    for channel_id in project_channel_ids(channel.id) {
+      move_to_family(http, channel_id, family).await?;
+    }
+  }
+
+  let new_status = status.to_string();
+  let updated = {
+    let mut db_instance = INSTANCE.write().unwrap();
+    let updated = db_instance
+      .project_update(
+        project.id,
+        ProjectChangeset {
+          status: Some(&new_status),
+          deleted_at: Some((status == ProjectStatus::Deleted).then(SystemTime::now)),
+          ..Default::default()
+        },
+      )?
+      .clone();
+    db_instance.project_history_add(&[NewProjectChange {
+      project_id: project.id,
+      author: author.0 as i64,
+      field: "status",
+      old_value: &project.status,
+      new_value: &new_status,
+    }])?;
+    updated
+  };
+  if let Some(deletion) = deletion_date(&updated) {
+    scheduler::schedule(deletion, Job::ProjectDeletion(updated.id));
+  }
+  schedule_reminders(&updated);
+  update_fiche(http, &updated, date_parse::user_timezone(author.0)).await?;
+  refresh_directory(http).await;
+  Ok(updated)
+}
+
+async fn change_status(
+  params: &CallBackParams<'_>,
+  status: ProjectStatus,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let project = match find_project(params.message, &params.args[2..]) {
+    Ok((project, [])) => project,
+    Ok(_) => return Ok(String::from(PROJECT_USAGE)),
+    Err(error) => return Ok(error),
+  };
+  let author = &params.message.author;
+  if permissions::user_role(author.id) < Role::Admin
+    && !is_project_lead(ChannelId(project.channel_id as u64), author)
+  {
+    return Ok(String::from(
+      "Only admins and the lead of the project can change its status",
+    ));
+  }
+  let current = project.status();
+  if current == status {
+    return Ok(format!("<#{}> is already {}", project.channel_id, status));
+  }
+  if current == ProjectStatus::Deleted && status != ProjectStatus::Active {
+    return Ok(format!(
+      "<#{}> is deleted, restore it first",
+      project.channel_id
+    ));
+  }
+  set_project_status(&params.context.http, &project, status, author.id).await?;
+  Ok(String::from(":ok:"))
+}
+
+/// Project of the channel given as first argument, or else of the channel of the message.
+fn find_project<'a>(
+  message: &Message,
+  args: &'a [String],
+) -> Result<(Project, &'a [String]), String> {
+  let (channel, args) = match args.first() {
+    Some(channel) if channel.starts_with("<#") => {
+      let (channel, _) = discord_str_to_id(channel, Some(DiscordIds::Channel))?;
+      (ChannelId(channel), &args[1..])
+    }
+    _ => (message.channel_id, args),
+  };
+  let db_instance = INSTANCE.read().unwrap();
+  match db_instance.projects_search(channel.0 as i64, DiscordIds::Channel) {
+    Some((_, project)) => Ok((project.clone(), args)),
+    None => Err(format!("<#{}> is not a project channel", channel)),
+  }
+}
+
+async fn edit_project(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let (project, fields) = match find_project(params.message, &params.args[2..]) {
+    Ok(found) => found,
+    Err(error) => return Ok(error),
+  };
+  if fields.is_empty() {
+    return Ok(String::from(PROJECT_USAGE));
+  }
+  let author = &params.message.author;
+  if permissions::user_role(author.id) < Role::Admin
+    && !is_project_lead(ChannelId(project.channel_id as u64), author)
+  {
+    return Ok(String::from(
+      "Only admins and the lead of the project can edit its fiche",
+    ));
+  }
+
+  let mut changes = ProjectChangeset::default();
+  let mut history = Vec::new();
+  for field in fields {
+    let Some((name, value)) = field.split_once('=') else {
+      return Ok(format!("Expected field=value, got: {}", field));
+    };
+    let (target, old_value) = match name {
+      "codex" => (&mut changes.codex, &project.codex),
+      "client" => (&mut changes.client, &project.client),
+      "lead" => (&mut changes.lead, &project.lead),
+      "deadline" => (&mut changes.deadline, &project.deadline),
+      "description" => (&mut changes.description, &project.description),
+      "contexte" => (&mut changes.contexte, &project.contexte),
+      _ => {
+        return Ok(format!(
+          "Invalid field: {}, expected one of {}",
+          name,
+          ARGUMENT_LIST.join(", ")
+        ))
+      }
+    };
+    *target = Some(value);
+    if old_value != value {
+      history.push(NewProjectChange {
+        project_id: project.id,
+        author: author.id.0 as i64,
+        field: name,
+        old_value,
+        new_value: value,
+      });
+    }
+  }
+  if history.is_empty() {
+    return Ok(String::from("Nothing changed"));
+  }
+
+  let now = date_parse::user_now(author.id.0);
+  set_deadline_date(&mut changes, now.date_naive());
+  let updated = {
+    let mut db_instance = INSTANCE.write().unwrap();
+    let updated = db_instance.project_update(project.id, changes)?.clone();
+    db_instance.project_history_add(&history)?;
+    updated
+  };
+  schedule_reminders(&updated);
+  update_fiche(&params.context.http, &updated, now.timezone()).await?;
+  refresh_directory(&params.context.http).await;
+  let fields: Vec<&str> = history.iter().map(|change| change.field).collect();
+  Ok(format!(
+    "Updated {} of <#{}>",
+    fields.join(", "),
+    project.channel_id
+  ))
+}
+
+fn project_history(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let project = match find_project(params.message, &params.args[2..]) {
+    Ok((project, [])) => project,
+    Ok(_) => return Ok(String::from(PROJECT_USAGE)),
+    Err(error) => return Ok(error),
+  };
+  let timezone = date_parse::user_timezone(params.message.author.id.0);
+  let changes = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance.project_history(project.id)?
+  };
+  if changes.is_empty() {
+    return Ok(format!(
+      "The fiche of <#{}> didn't change since its creation",
+      project.channel_id
+    ));
+  }
+  let mut reply = format!("Changes of the fiche of <#{}>:\n", project.channel_id);
+  for change in changes {
+    writeln!(
+      reply,
+      "{} <@{}> {}: `{}` => `{}`",
+      date_parse::format_user_date_time(change.date.and_utc(), timezone),
+      change.author,
+      change.field,
+      change.old_value,
+      change.new_value
+    )
+    .expect("unable to append string");
+  }
+  Ok(reply)
+}
+
+#[command]
+pub async fn project(params: CallBackParams) -> CallbackReturn {
+  let reply = match params.args[1].as_str() {
+    "edit" => edit_project(&params).await?,
+    "history" => project_history(&params)?,
+    "members" => members::members(&params).await?,
+    "access" => access::access(&params).await?,
+    "template" => templates::template(&params).await?,
+    "directory" => {
+      if permissions::user_role(params.message.author.id) < Role::Admin {
+        return Ok(Some(String::from("Only admins can post the directory")));
+      }
+      post_directory(&params.context.http).await?;
+      String::from(":ok:")
+    }
+    "archive" => change_status(&params, ProjectStatus::Archived).await?,
+    "hold" => change_status(&params, ProjectStatus::OnHold).await?,
+    "restore" => change_status(&params, ProjectStatus::Active).await?,
+    "migrate-fiches" => {
+      let author = params.message.author.id;
+      if permissions::user_role(author) < Role::Admin {
+        return Ok(Some(String::from("Only admins can migrate the fiches")));
+      }
+      let timezone = date_parse::user_timezone(author.0);
+      let (updated, total) = migrate_fiches(&params.context.http, timezone).await;
+      format!("Migrated {}/{} fiches", updated, total)
+    }
+    _ => String::from(PROJECT_USAGE),
+  };
+  Ok(Some(reply))
+}
+
+async fn create_read_permission(
+  context: &Context,
+  guildchannel: &GuildChannel,
+  userid: u64,
+  state: ReadState,
+) -> Result<Option<String>, String> {
+  let allowed = matches!(state, ReadState::Allow);
+  let message = if allowed {
+    Ok(Some(format!("Added <@{}> Welcome !", userid)))
+  } else {
+    Ok(Some(format!("Removed <@{}>", userid)))
+  };
+  let overwrite = member_channel_read(UserId(userid), state);
+  for channel_id in project_channel_ids(guildchannel.id) {
+    channel_id
+      .create_permission(&context.http, &overwrite)
+      .await
+      .unwrap();
+  }
+  if allowed {
+    record_join(guildchannel.id, UserId(userid), MemberSource::Command);
+  } else {
+    record_leave(guildchannel.id, UserId(userid));
+  }
+  message
+}
+
+pub async fn user_view(
+  params: CallBackParams<'_>,
+  state: ReadState,
+) -> Result<Option<String>, String> {
+  let cache_http = &params.context;
+  let usertag = &params.args[1];
+
+  match params
+    .message
+    .channel(cache_http)
+    .await
+    .expect("Channel of message wasn't found")
+  {
+    Channel::Guild(guildchannel) => {
+      match parse::discord_str_to_id(usertag, Some(parse::DiscordIds::User)) {
+        Ok((userid, _)) => {
+          create_read_permission(params.context, &guildchannel, userid, state).await
+        }
+        Err(_error) => {
+          if let Some(guild) = &guildchannel.guild(cache_http) {
+            let member = guild.member_named(usertag);
+            if let Some(member) = member {
+              let userid = member.user.id.0;
+              create_read_permission(params.context, &guildchannel, userid, state).await
+            } else {
+              check_containing(params.context, guild, usertag, guildchannel).await
+            }
+          } else {
+            panic!("Unable to get guild from cache")
+          }
+        }
+      }
+    }
+    _ => Ok(Some(String::from(
+      "This command is restricted to a guild channel",
+    ))),
+  }
+}
+
+#[derive(Debug)]
+struct StringError(String);
+
+impl Display for StringError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl Error for StringError {}
+
+#[command]
+pub async fn remove_user(params: CallBackParams<'_>) -> CallbackReturn<'_> {
+  user_view(params, ReadState::Deny)
+    .await
+    .map_err(|s| Box::new(StringError(s)) as Box<dyn Error + Sync + Send>)
+}
+
+#[command]
+pub async fn add_user(params: CallBackParams<'_>) -> CallbackReturn<'_> {
+  user_view(params, ReadState::Allow)
+    .await
+    .map_err(|s| Box::new(StringError(s)) as Box<dyn Error + Sync + Send>)
+}
+
+async fn check_containing(
+  context: &Context,
+  guild: &Guild,
+  usertag: &str,
+  guildchannel: GuildChannel,
+) -> Result<Option<String>, String> {
+  let members = guild.members_containing(usertag, false, false).await;
+  if !members.is_empty() {
+    if members.len() == 1 {
+      let userid = members[0].0.user.id.0;
+      create_read_permission(context, &guildchannel, userid, ReadState::Allow).await
+    } else {
+      let membernames: String = members.iter().fold(String::new(), |mut out, member| {
+        write!(out, "{}, ", member.1).unwrap();
+        out
+      });
+      Ok(Some(format!(
+        "Found too many users with this name: {},\n found: {}",
+        usertag, membernames
+      )))
+    }
+  } else {
+    Ok(Some(format!(
+      "Didn't find any user with {} in their name",
+      usertag
+    )))
+  }
+}
+
+pub fn is_project_channel(channel_id: ChannelId) -> bool {
+  let db_instance = INSTANCE.read().unwrap();
+  db_instance
+    .projects_search(channel_id.0 as i64, parse::DiscordIds::Channel)
+    .is_some()
+}
+
+/// Whether the user is the lead written in the fiche of the project of this channel.
+pub fn is_project_lead(channel_id: ChannelId, user: &User) -> bool {
+  let db_instance = INSTANCE.read().unwrap();
+  db_instance
+    .projects_search(channel_id.0 as i64, parse::DiscordIds::Channel)
+    .is_some_and(|(_, project)| {
+      project.lead.contains(&user.id.to_string()) || project.lead.eq_ignore_ascii_case(&user.name)
+    })
+}
+
+/// Join or leave the project of the announcement the user reacted to, following its access mode.
+pub async fn check_subscribe(ctx: &Context, reaction: &Reaction, removed: bool) {
+  let project = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .projects_search(reaction.message_id.0 as i64, parse::DiscordIds::Message)
+      .map(|(_, project)| project.clone())
+  };
+  let (Some(project), Some(user_id)) = (project, reaction.user_id) else {
+    return;
+  };
+
+  if removed {
+    if let Err(e) = leave_project(&ctx.http, &project, user_id).await {
+      error!(
+        "Unable to remove {} from project {}: {}",
+        user_id, project.id, e
+      );
+    }
+    return;
+  }
+  match join_project(&ctx.http, &project, user_id, MemberSource::Reaction).await {
+    Ok(outcome @ (JoinOutcome::Requested | JoinOutcome::Closed)) => {
+      let notice = outcome.describe(project.channel_id);
+      let notified = match user_id.create_dm_channel(&ctx.http).await {
+        Ok(dm) => dm.say(&ctx.http, notice).await.map(|_| ()),
+        Err(e) => Err(e),
+      };
+      if let Err(e) = notified {
+        error!("Unable to notify {}: {}", user_id, e);
+      }
+    }
+    Ok(_) => (),
+    Err(e) => error!("Unable to add {} to project {}: {}", user_id, project.id, e),
+  }
+}
+
+/// Remove the overwrites of a user from the channels of every project, archived ones included.
+pub async fn revoke_all_access(
+  http: &Http,
+  user_id: UserId,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let main_channels: Vec<ChannelId> = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance
+      .projects
+      .iter()
+      .map(|project| ChannelId(project.channel_id as u64))
+      .collect()
+  };
+  for main_channel in main_channels {
+    for channel_id in project_channel_ids(main_channel) {
+      match channel_id
+        .delete_permission(http, PermissionOverwriteType::Member(user_id))
+        .await
+      {
+        Err(error) if !is_not_found(&error) => return Err(error.into()),
+        _ => (),
+      }
+    }
+  }
+  Ok(())
+}
+
+#[command]
+pub async fn remove_user_from_all(params: CallBackParams<'_>) -> CallbackReturn {
+  let (useid, _) = discord_str_to_id(&params.args[1], Some(DiscordIds::User))?;
+  let channels = params
+    .message
+    .guild_id
+    .expect("Unable to find guildid in message")
+    .channels(&params.context.http)
+    .await
+    .unwrap();
+  let text_projects_channels: Vec<_> = channels
+    .iter()
+    .filter(|(_, chan)| {
+      chan.kind == ChannelType::Text
+        && chan
+          .parent_id
+          .is_some_and(|category| CategoryFamily::Projects.contains(category))
+    })
+    .collect();
+
+  for (channel_id, _) in text_projects_channels {
+    channel_id
+      .delete_permission(
+        &params.context.http,
+        PermissionOverwriteType::Member(UserId(useid)),
+      )
+      .await
+      .unwrap();
+    record_leave(*channel_id, UserId(useid));
+  }
+
+  Ok(Some(String::from(":ok:")))
+}
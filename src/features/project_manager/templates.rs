@@ -0,0 +1,356 @@
+//! Templates of the projects: the channels created along with the main channel of a project, the
+//! roles allowed to read them, the messages pinned and the members added.
+//!
+//! Templates are written in JSON and kept in the storage, for example:
+//! ```json
+//! {
+//!   "name": "web",
+//!   "pins": ["Repository: <URL>"],
+//!   "channels": [
+//!     { "name": "dev", "threads": ["releases"] },
+//!     { "name": "design", "kind": "forum" },
+//!     { "name": "call", "kind": "voice" }
+//!   ],
+//!   "roles": [822511597137559560],
+//!   "members": [227491479237525504]
+//! }
+//! ```
+use std::{error::Error, fmt::Write};
+
+use super::members::record_join;
+use crate::{
+  core::{commands::CallBackParams, parse, permissions},
+  database::{
+    MemberSource, NewProjectChannel, NewStorage, Project, Role, StorageDataType, INSTANCE,
+  },
+  features::categories::{create_in_family, CategoryFamily},
+};
+use serenity::{
+  http::Http,
+  model::{
+    channel::{ChannelType, PermissionOverwrite, PermissionOverwriteType},
+    id::{ChannelId, RoleId, UserId},
+    Permissions,
+  },
+};
+
+const TEMPLATE_USAGE: &str =
+  "Usage: @BOT project template list | show <name> | set (with a JSON file) | remove <name>";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateChannelKind {
+  #[default]
+  Text,
+  Voice,
+  Forum,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TemplateChannel {
+  /// Appended to the name of the project
+  pub name: String,
+  #[serde(default)]
+  pub kind: TemplateChannelKind,
+  #[serde(default)]
+  pub topic: Option<String>,
+  /// Messages pinned in a text channel
+  #[serde(default)]
+  pub pins: Vec<String>,
+  /// Threads opened in a text channel
+  #[serde(default)]
+  pub threads: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProjectTemplate {
+  pub name: String,
+  /// Messages pinned in the main channel, after the fiche
+  #[serde(default)]
+  pub pins: Vec<String>,
+  #[serde(default)]
+  pub channels: Vec<TemplateChannel>,
+  /// Roles allowed to read all the channels
+  #[serde(default)]
+  pub roles: Vec<u64>,
+  /// Users added to the project
+  #[serde(default)]
+  pub members: Vec<u64>,
+}
+
+impl ProjectTemplate {
+  fn parse(json: &str) -> Result<Self, String> {
+    let template: ProjectTemplate =
+      serde_json::from_str(json).map_err(|e| format!("Invalid template: {}", e))?;
+    if template.name.is_empty() || template.name.contains(char::is_whitespace) {
+      return Err(String::from(
+        "The name of the template must be a single word",
+      ));
+    }
+    for channel in &template.channels {
+      if channel.name.is_empty() {
+        return Err(String::from("A channel of the template has no name"));
+      }
+      let has_messages = !channel.pins.is_empty() || !channel.threads.is_empty();
+      if channel.kind != TemplateChannelKind::Text && has_messages {
+        return Err(format!(
+          "Only text channels can have pins and threads: {}",
+          channel.name
+        ));
+      }
+    }
+    Ok(template)
+  }
+}
+
+impl TemplateChannelKind {
+  fn channel_type(self) -> ChannelType {
+    match self {
+      TemplateChannelKind::Text => ChannelType::Text,
+      TemplateChannelKind::Voice => ChannelType::Voice,
+      TemplateChannelKind::Forum => ChannelType::Forum,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      TemplateChannelKind::Text => "text",
+      TemplateChannelKind::Voice => "voice",
+      TemplateChannelKind::Forum => "forum",
+    }
+  }
+}
+
+/// The stored templates along with the id of their storage entry.
+fn stored_templates() -> Vec<(i32, ProjectTemplate)> {
+  let db_instance = INSTANCE.read().unwrap();
+  db_instance
+    .filter_storage_type(StorageDataType::ProjectTemplate)
+    .into_iter()
+    .filter_map(|stored| Some((stored.id, ProjectTemplate::parse(&stored.data).ok()?)))
+    .collect()
+}
+
+pub fn find_template(name: &str) -> Option<ProjectTemplate> {
+  stored_templates()
+    .into_iter()
+    .map(|(_, template)| template)
+    .find(|template| template.name == name)
+}
+
+async fn pin_messages(
+  http: &Http,
+  channel_id: ChannelId,
+  pins: &[String],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  for pin in pins {
+    channel_id.say(http, pin).await?.pin(http).await?;
+  }
+  Ok(())
+}
+
+/// The main channel of the project followed by the channels created by its template, `channel`
+/// being any of them. A channel that isn't part of a project is returned alone.
+pub fn project_channel_ids(channel: ChannelId) -> Vec<ChannelId> {
+  let db_instance = INSTANCE.read().unwrap();
+  let project = db_instance
+    .projects_search(channel.0 as i64, parse::DiscordIds::Channel)
+    .map(|(_, project)| project.id)
+    .or_else(|| {
+      db_instance
+        .project_channels
+        .iter()
+        .find(|linked| linked.channel_id == channel.0 as i64)
+        .map(|linked| linked.project_id)
+    })
+    .and_then(|project_id| {
+      db_instance
+        .projects
+        .iter()
+        .find(|project| project.id == project_id)
+    });
+  let Some(project) = project else {
+    return vec![channel];
+  };
+  let mut channels = vec![ChannelId(project.channel_id as u64)];
+  channels.extend(
+    db_instance
+      .project_channels
+      .iter()
+      .filter(|linked| linked.project_id == project.id)
+      .map(|linked| ChannelId(linked.channel_id as u64)),
+  );
+  channels
+}
+
+/// Create the channels of the template next to the main channel of the project, then give the
+/// roles and members of the template access to all of them.
+pub async fn apply_template(
+  http: &Http,
+  project: &Project,
+  name: &str,
+  template: &ProjectTemplate,
+  author: UserId,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let main_channel = ChannelId(project.channel_id as u64);
+  pin_messages(http, main_channel, &template.pins).await?;
+  let mut channels = vec![main_channel];
+  for template_channel in &template.channels {
+    let channel = create_in_family(http, CategoryFamily::Projects, |channel| {
+      channel
+        .name(format!("{}-{}", name, template_channel.name))
+        .kind(template_channel.kind.channel_type());
+      if let Some(topic) = &template_channel.topic {
+        channel.topic(topic);
+      }
+      channel
+    })
+    .await?;
+    {
+      let mut db_instance = INSTANCE.write().unwrap();
+      db_instance.project_channel_add(NewProjectChannel {
+        project_id: project.id,
+        channel_id: channel.id.0 as i64,
+        kind: template_channel.kind.name(),
+      });
+    }
+    let overwrite = permissions::member_channel_read(author, permissions::ReadState::Allow);
+    channel.id.create_permission(http, &overwrite).await?;
+    pin_messages(http, channel.id, &template_channel.pins).await?;
+    for thread in &template_channel.threads {
+      // Public threads start from a message, they are visible to the readers of the channel
+      let starter = channel.id.say(http, thread).await?;
+      channel
+        .id
+        .create_public_thread(http, starter.id, |create| create.name(thread))
+        .await?;
+    }
+    channels.push(channel.id);
+  }
+
+  let roles = template.roles.iter().map(|role| PermissionOverwrite {
+    allow: Permissions::VIEW_CHANNEL,
+    deny: Permissions::empty(),
+    kind: PermissionOverwriteType::Role(RoleId(*role)),
+  });
+  let members = template
+    .members
+    .iter()
+    .map(|member| permissions::member_channel_read(UserId(*member), permissions::ReadState::Allow));
+  let overwrites: Vec<PermissionOverwrite> = roles.chain(members).collect();
+  for channel in &channels {
+    for overwrite in &overwrites {
+      channel.create_permission(http, overwrite).await?;
+    }
+  }
+  for member in &template.members {
+    record_join(main_channel, UserId(*member), MemberSource::Template);
+  }
+  Ok(())
+}
+
+fn describe_template(template: &ProjectTemplate) -> String {
+  let channels: Vec<String> = template
+    .channels
+    .iter()
+    .map(|channel| format!("{} ({})", channel.name, channel.kind.name()))
+    .collect();
+  format!(
+    "`{}`: {} · {} roles · {} members",
+    template.name,
+    if channels.is_empty() {
+      String::from("main channel only")
+    } else {
+      channels.join(", ")
+    },
+    template.roles.len(),
+    template.members.len()
+  )
+}
+
+async fn set_template(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let Some(attachment) = params.message.attachments.first() else {
+    return Ok(String::from("Attach the template as a JSON file"));
+  };
+  let json = String::from_utf8(attachment.download().await?)?;
+  let template = match ProjectTemplate::parse(&json) {
+    Ok(template) => template,
+    Err(error) => return Ok(error),
+  };
+  let existing: Vec<i32> = stored_templates()
+    .into_iter()
+    .filter(|(_, stored)| stored.name == template.name)
+    .map(|(id, _)| id)
+    .collect();
+  let mut db_instance = INSTANCE.write().unwrap();
+  db_instance.storage_delete(existing);
+  db_instance.storage_add(NewStorage {
+    datatype: StorageDataType::ProjectTemplate.into(),
+    dataid: None,
+    data: &serde_json::to_string(&template)?,
+    date: None,
+  });
+  Ok(format!("Saved {}", describe_template(&template)))
+}
+
+pub async fn template(params: &CallBackParams<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let action = params.args.get(2).map(String::as_str);
+  if matches!(action, Some("set" | "remove"))
+    && permissions::user_role(params.message.author.id) < Role::Admin
+  {
+    return Ok(String::from("Only admins can change the templates"));
+  }
+  let reply = match (action, params.args.get(3)) {
+    (Some("list") | None, None) => {
+      let templates = stored_templates();
+      if templates.is_empty() {
+        return Ok(String::from("No project template"));
+      }
+      let mut reply = String::from("Project templates:\n");
+      for (_, template) in templates {
+        writeln!(reply, "{}", describe_template(&template)).expect("unable to append string");
+      }
+      reply
+    }
+    (Some("show"), Some(name)) => match find_template(name) {
+      Some(template) => format!("```json\n{}\n```", serde_json::to_string_pretty(&template)?),
+      None => format!("Unknown template: {}", name),
+    },
+    (Some("set"), None) => set_template(params).await?,
+    (Some("remove"), Some(name)) => {
+      let existing: Vec<i32> = stored_templates()
+        .into_iter()
+        .filter(|(_, template)| &template.name == name)
+        .map(|(id, _)| id)
+        .collect();
+      if existing.is_empty() {
+        return Ok(format!("Unknown template: {}", name));
+      }
+      let mut db_instance = INSTANCE.write().unwrap();
+      db_instance.storage_delete(existing);
+      String::from(":ok:")
+    }
+    _ => String::from(TEMPLATE_USAGE),
+  };
+  Ok(reply)
+}
+
+#[test]
+fn test_parse_template() {
+  let template = ProjectTemplate::parse(
+    r#"{"name": "web", "channels": [{"name": "dev", "threads": ["releases"]}, {"name": "call", "kind": "voice"}]}"#,
+  )
+  .unwrap();
+  assert_eq!(template.channels[0].kind, TemplateChannelKind::Text);
+  assert_eq!(template.channels[1].kind, TemplateChannelKind::Voice);
+  assert!(template.members.is_empty());
+  assert!(ProjectTemplate::parse(r#"{"name": "two words"}"#).is_err());
+  assert!(ProjectTemplate::parse(
+    r#"{"name": "web", "channels": [{"name": "call", "kind": "voice", "pins": ["hi"]}]}"#
+  )
+  .is_err());
+  assert!(ProjectTemplate::parse(
+    r#"{"name": "web", "channels": [{"name": "x", "kind": "stage"}]}"#
+  )
+  .is_err());
+}
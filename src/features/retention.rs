@@ -0,0 +1,295 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::Write,
+  time::{Duration, SystemTime},
+};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    parse::{self, DiscordIds},
+    scheduler::{self, Job},
+  },
+  database::{NewRetentionPolicy, RetentionPolicy, INSTANCE},
+};
+use chrono::Utc;
+use log::info;
+use procedural_macros::command;
+use serenity::{
+  http::{Http, StatusCode},
+  model::{
+    channel::{Channel, ChannelType, GuildChannel},
+    id::{ChannelId, GuildId},
+  },
+  prelude::*,
+  Error as SerenityError,
+};
+
+const RETENTION_CHECK_SECS: i64 = 60 * 60;
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Retention {
+  Forever,
+  Days(i32),
+  DoNotStore,
+  /// Remove the policy, falling back to the guild default
+  Default,
+}
+
+fn parse_retention(input: &str) -> Result<Retention, String> {
+  match input {
+    "forever" => Ok(Retention::Forever),
+    "nostore" => Ok(Retention::DoNotStore),
+    "default" => Ok(Retention::Default),
+    _ => match input.strip_suffix('d').map(str::parse::<i32>) {
+      Some(Ok(days)) if days > 0 => Ok(Retention::Days(days)),
+      _ => Err(format!(
+        "Invalid retention: {}, expected forever, nostore, default or a number of days like 30d",
+        input
+      )),
+    },
+  }
+}
+
+fn describe_policy(policy: &RetentionPolicy) -> String {
+  let target = match policy.channel_id {
+    Some(channel) => format!("<#{}>", channel),
+    None => String::from("guild default"),
+  };
+  let retention = match (policy.store, policy.keep_days) {
+    (false, _) => String::from("not stored"),
+    (true, Some(days)) => format!("kept {} days", days),
+    (true, None) => String::from("kept forever"),
+  };
+  format!("{}: {}", target, retention)
+}
+
+#[command]
+pub async fn retention(params: CallBackParams) -> CallbackReturn {
+  let Some(guild_id) = params.message.guild_id else {
+    return Ok(Some(String::from(
+      "This command is restricted to a guild channel",
+    )));
+  };
+  let guild_id = guild_id.0 as i64;
+
+  if params.args.len() == 1 {
+    let db_instance = INSTANCE.read().unwrap();
+    let mut reply = String::from("Retention policies (messages are kept forever by default):\n");
+    for policy in db_instance
+      .retention_policies
+      .iter()
+      .filter(|policy| policy.guild_id == guild_id)
+    {
+      writeln!(reply, "{}", describe_policy(policy)).expect("unable to append string");
+    }
+    return Ok(Some(reply));
+  }
+  if params.args.len() != 3 {
+    return Ok(Some(String::from(
+      "Usage: @BOT retention [<#channel|guild> <forever|nostore|default|<N>d>]",
+    )));
+  }
+
+  let channel_id = match params.args[1].as_str() {
+    "guild" => None,
+    channel => match parse::discord_str_to_id(channel, Some(DiscordIds::Channel)) {
+      Ok((channel_id, _)) => Some(channel_id as i64),
+      Err(error) => return Ok(Some(error)),
+    },
+  };
+  let retention = match parse_retention(&params.args[2]) {
+    Ok(retention) => retention,
+    Err(error) => return Ok(Some(error)),
+  };
+
+  let mut db_instance = INSTANCE.write().unwrap();
+  let (keep_days, store) = match retention {
+    Retention::Forever => (None, true),
+    Retention::Days(days) => (Some(days), true),
+    Retention::DoNotStore => (None, false),
+    Retention::Default => {
+      db_instance.retention_policy_delete(guild_id, channel_id)?;
+      return Ok(Some(String::from(":ok:")));
+    }
+  };
+  db_instance.retention_policy_set(NewRetentionPolicy {
+    guild_id,
+    channel_id,
+    keep_days,
+    store,
+  })?;
+  Ok(Some(String::from(":ok:")))
+}
+
+/// Parent channel of a thread, `None` for the other channels.
+pub fn thread_parent(
+  ctx: &Context,
+  guild_id: Option<GuildId>,
+  channel_id: ChannelId,
+) -> Option<i64> {
+  ctx
+    .cache
+    .guild_field(guild_id?, |guild| {
+      guild
+        .threads
+        .iter()
+        .find(|thread| thread.id == channel_id)
+        .and_then(|thread| thread.parent_id)
+    })
+    .flatten()
+    .map(|parent| parent.0 as i64)
+}
+
+fn is_thread(channel: &GuildChannel) -> bool {
+  matches!(
+    channel.kind,
+    ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+  )
+}
+
+/// Guild and parent channel, for the threads, of the channels with stored messages.
+///
+/// Channels deleted since are placed in the main guild without parent.
+async fn locate_channels(
+  http: &Http,
+  policies: &[RetentionPolicy],
+  channels: &HashSet<i64>,
+) -> Result<HashMap<i64, (i64, Option<i64>)>, Box<dyn std::error::Error + Send + Sync>> {
+  let mut places = HashMap::new();
+  let guilds: HashSet<i64> = policies.iter().map(|policy| policy.guild_id).collect();
+  for guild in guilds {
+    let guild_id = GuildId(guild as u64);
+    for channel in guild_id.channels(http).await?.into_keys() {
+      places.insert(channel.0 as i64, (guild, None));
+    }
+    for thread in guild_id.get_active_threads(http).await?.threads {
+      let parent = thread.parent_id.map(|parent| parent.0 as i64);
+      places.insert(thread.id.0 as i64, (guild, parent));
+    }
+  }
+  let unknown: Vec<i64> = channels
+    .iter()
+    .filter(|channel| !places.contains_key(channel))
+    .copied()
+    .collect();
+  // Archived threads and deleted channels
+  for channel in unknown {
+    let place = match ChannelId(channel as u64).to_channel(http).await {
+      Ok(Channel::Guild(found)) => (
+        found.guild_id.0 as i64,
+        found
+          .parent_id
+          .filter(|_| is_thread(&found))
+          .map(|parent| parent.0 as i64),
+      ),
+      Ok(_) => continue,
+      Err(SerenityError::Http(error)) if error.status_code() == Some(StatusCode::NOT_FOUND) => {
+        (parse::main_guild_id().0 as i64, None)
+      }
+      Err(error) => return Err(error.into()),
+    };
+    places.insert(channel, place);
+  }
+  Ok(places)
+}
+
+/// Delete the messages that expired according to the policy of their channel.
+///
+/// Threads follow the policy of their parent channel unless they have their own, and the channels
+/// without a policy follow the default of their guild, deleted channels included.
+async fn enforce_retention(http: &Http) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let (policies, channels) = {
+    let db_instance = INSTANCE.read().unwrap();
+    let channels: HashSet<i64> = db_instance
+      .messages
+      .iter()
+      .map(|message| message.channel)
+      .collect();
+    (db_instance.retention_policies.clone(), channels)
+  };
+  if policies.is_empty() {
+    return Ok(());
+  }
+  let places = locate_channels(http, &policies, &channels).await?;
+  let mut channels_by_policy: HashMap<i32, Vec<i64>> = HashMap::new();
+  for (channel, (guild, parent)) in places {
+    if !channels.contains(&channel) {
+      continue;
+    }
+    if let Some(policy) = RetentionPolicy::applying_to(&policies, Some(guild), channel, parent) {
+      channels_by_policy
+        .entry(policy.id)
+        .or_default()
+        .push(channel);
+    }
+  }
+  let now = SystemTime::now();
+
+  for policy in &policies {
+    let Some(channels) = channels_by_policy.get(&policy.id) else {
+      continue;
+    };
+    let keep_days = match (policy.store, policy.keep_days) {
+      (false, _) => 0,
+      (true, Some(days)) => days as u64,
+      (true, None) => continue,
+    };
+    let before = now - Duration::from_secs(keep_days * DAY_SECS);
+    let mut db_instance = INSTANCE.write().unwrap();
+    let deleted = db_instance.messages_purge(channels, before)?;
+    if deleted > 0 {
+      info!(
+        "Retention: deleted {} messages ({})",
+        deleted,
+        describe_policy(policy)
+      );
+    }
+  }
+  Ok(())
+}
+
+/// Delete the messages that are past their retention, then run again in an hour.
+pub async fn retention_job(http: &Http) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  scheduler::schedule(
+    Utc::now() + chrono::Duration::seconds(RETENTION_CHECK_SECS),
+    Job::Retention,
+  );
+  enforce_retention(http).await
+}
+
+#[test]
+fn test_parse_retention() {
+  assert_eq!(parse_retention("forever"), Ok(Retention::Forever));
+  assert_eq!(parse_retention("nostore"), Ok(Retention::DoNotStore));
+  assert_eq!(parse_retention("90d"), Ok(Retention::Days(90)));
+  assert!(parse_retention("0d").is_err());
+  assert!(parse_retention("90").is_err());
+}
+
+#[test]
+fn test_policy_applying_to() {
+  let policy = |id, channel_id, keep_days| RetentionPolicy {
+    id,
+    guild_id: 1,
+    channel_id,
+    keep_days,
+    store: true,
+  };
+  let policies = [
+    policy(1, None, Some(30)),
+    policy(2, Some(10), None),
+    policy(3, Some(11), Some(7)),
+  ];
+  let applying = |channel, parent| {
+    RetentionPolicy::applying_to(&policies, Some(1), channel, parent).map(|policy| policy.id)
+  };
+  assert_eq!(applying(10, None), Some(2));
+  // A thread of channel 10, then a thread with its own policy
+  assert_eq!(applying(20, Some(10)), Some(2));
+  assert_eq!(applying(11, Some(10)), Some(3));
+  // Unknown or deleted channels
+  assert_eq!(applying(30, None), Some(1));
+  assert!(RetentionPolicy::applying_to(&policies, Some(2), 30, None).is_none());
+}
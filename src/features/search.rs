@@ -0,0 +1,213 @@
+use std::{fmt::Write, time::SystemTime};
+
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+    parse::{self, DiscordIds},
+  },
+  database::{MessageSearch, INSTANCE},
+};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
+use procedural_macros::command;
+use serenity::{
+  model::{
+    channel::ChannelType,
+    id::{GuildId, UserId},
+    Permissions,
+  },
+  prelude::*,
+};
+
+const RESULTS_PER_PAGE: i64 = 10;
+const PREVIEW_LENGTH: usize = 120;
+
+#[derive(Debug, PartialEq, Eq)]
+struct SearchRequest {
+  terms: String,
+  author: Option<u64>,
+  channel: Option<u64>,
+  before: Option<NaiveDate>,
+  after: Option<NaiveDate>,
+  page: i64,
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+  date_parse::parse_day(&date.to_lowercase(), Utc::now().date_naive())
+    .ok_or_else(|| format!("Invalid date: {}, expected YYYY-MM-DD", date))
+}
+
+fn parse_search_args(args: &[String]) -> Result<SearchRequest, String> {
+  let mut request = SearchRequest {
+    terms: String::new(),
+    author: None,
+    channel: None,
+    before: None,
+    after: None,
+    page: 1,
+  };
+  let mut terms = Vec::new();
+  for arg in args {
+    match arg.split_once(':') {
+      Some(("from", user)) => {
+        request.author = Some(parse::discord_str_to_id(user, Some(DiscordIds::User))?.0)
+      }
+      Some(("in", channel)) => {
+        request.channel = Some(parse::discord_str_to_id(channel, Some(DiscordIds::Channel))?.0)
+      }
+      Some(("before", date)) => request.before = Some(parse_date(date)?),
+      Some(("after", date)) => request.after = Some(parse_date(date)?),
+      Some(("page", page)) => match page.parse() {
+        Ok(page) if page > 0 => request.page = page,
+        _ => return Err(format!("Invalid page: {}", page)),
+      },
+      _ => terms.push(arg.as_str()),
+    }
+  }
+  if terms.is_empty() {
+    return Err(String::from("Missing search terms"));
+  }
+  request.terms = terms.join(" ");
+  Ok(request)
+}
+
+/// Midnight of `date` in the time zone of the user searching.
+fn date_to_system_time(date: NaiveDate, timezone: Tz) -> SystemTime {
+  let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+  date_parse::from_local(timezone, midnight)
+    .map(|date| date.with_timezone(&Utc).into())
+    .unwrap_or_else(|| midnight.and_utc().into())
+}
+
+/// Channels (and their threads) of the guild the user is able to read.
+pub async fn readable_channels(context: &Context, guild_id: GuildId, user_id: UserId) -> Vec<u64> {
+  let Some(guild) = context.cache.guild(guild_id) else {
+    return Vec::new();
+  };
+  let Ok(member) = guild.member(context, user_id).await else {
+    return Vec::new();
+  };
+  let readable: Vec<u64> = guild
+    .channels
+    .values()
+    .filter_map(|channel| channel.clone().guild())
+    .filter(|channel| channel.kind != ChannelType::Category)
+    .filter(|channel| {
+      guild
+        .user_permissions_in(channel, &member)
+        .map(|permissions| permissions.contains(Permissions::VIEW_CHANNEL))
+        .unwrap_or(false)
+    })
+    .map(|channel| channel.id.0)
+    .collect();
+  let threads = guild
+    .threads
+    .iter()
+    .filter(|thread| matches!(thread.parent_id, Some(parent) if readable.contains(&parent.0)))
+    .map(|thread| thread.id.0);
+  threads.chain(readable.iter().copied()).collect()
+}
+
+#[command]
+pub async fn search(params: CallBackParams) -> CallbackReturn {
+  let request = match parse_search_args(&params.args[1..]) {
+    Ok(request) => request,
+    Err(error) => return Ok(Some(error)),
+  };
+  let Some(guild_id) = params.message.guild_id else {
+    return Ok(Some(String::from(
+      "This command is restricted to a guild channel",
+    )));
+  };
+
+  let timezone = date_parse::user_timezone(params.message.author.id.0);
+  let mut channels = readable_channels(params.context, guild_id, params.message.author.id).await;
+  if let Some(channel) = request.channel {
+    channels.retain(|readable| *readable == channel);
+  }
+  let search = MessageSearch {
+    terms: &request.terms,
+    author: request.author.map(|author| author as i64),
+    channels: channels.into_iter().map(|channel| channel as i64).collect(),
+    before: request
+      .before
+      .map(|date| date_to_system_time(date, timezone)),
+    after: request
+      .after
+      .map(|date| date_to_system_time(date, timezone)),
+    bot_id: params.context.cache.current_user_id().0 as i64,
+  };
+  let (total, results) = {
+    let db_instance = INSTANCE.read().unwrap();
+    db_instance.message_search(
+      &search,
+      (request.page - 1) * RESULTS_PER_PAGE,
+      RESULTS_PER_PAGE,
+    )?
+  };
+  if results.is_empty() {
+    return Ok(Some(format!("No results for: {}", request.terms)));
+  }
+
+  let pages = (total + RESULTS_PER_PAGE - 1) / RESULTS_PER_PAGE;
+  let mut reply = format!(
+    "{} results for **{}**, page {}/{}:\n",
+    total, request.terms, request.page, pages
+  );
+  for message in results {
+    let author = match params.context.cache.user(message.author as u64) {
+      Some(user) => user.name,
+      None => message.author.to_string(),
+    };
+    let date = message
+      .date
+      .map(|date| date_parse::format_user_date_time(date, timezone))
+      .unwrap_or_default();
+    let mut preview: String = message.content.chars().take(PREVIEW_LENGTH).collect();
+    if preview.len() < message.content.len() {
+      preview.push('…');
+    }
+    writeln!(
+      reply,
+      "`{}` **{}**: {} <{}>",
+      date,
+      author,
+      preview.replace('\n', " "),
+      parse::message_url(guild_id.0, message.channel as u64, message.id as u64)
+    )
+    .expect("unable to append string");
+  }
+  if request.page < pages {
+    write!(reply, "Next page with `page:{}`", request.page + 1).expect("unable to append string");
+  }
+  Ok(Some(reply))
+}
+
+#[test]
+fn test_parse_search_args() {
+  let args: Vec<String> = [
+    "deploy",
+    "staging",
+    "from:<@173013989180178432>",
+    "in:<#852815758911340565>",
+    "after:2024-01-31",
+    "page:2",
+  ]
+  .iter()
+  .map(|arg| arg.to_string())
+  .collect();
+  assert_eq!(
+    parse_search_args(&args),
+    Ok(SearchRequest {
+      terms: String::from("deploy staging"),
+      author: Some(173013989180178432),
+      channel: Some(852815758911340565),
+      before: None,
+      after: NaiveDate::from_ymd_opt(2024, 1, 31),
+      page: 2,
+    })
+  );
+  assert!(parse_search_args(&[String::from("from:<@173013989180178432>")]).is_err());
+  assert!(parse_search_args(&[String::from("test"), String::from("page:0")]).is_err());
+}
@@ -0,0 +1,46 @@
+use crate::{
+  core::{
+    commands::{CallBackParams, CallbackReturn},
+    date_parse,
+  },
+  database::INSTANCE,
+};
+use chrono_tz::Tz;
+use procedural_macros::command;
+
+#[command]
+pub async fn timezone(params: CallBackParams) -> CallbackReturn {
+  let author = params.message.author.id.0;
+  let new_timezone = match (params.args.get(1).map(String::as_str), params.args.get(2)) {
+    (None, _) => {
+      let now = date_parse::user_now(author);
+      return Ok(Some(format!(
+        "Your time zone is {}, it's {}",
+        now.timezone(),
+        date_parse::format_date_time(&now)
+      )));
+    }
+    (Some("set"), Some(name)) => match name.parse::<Tz>() {
+      Ok(timezone) => Some(timezone),
+      Err(_) => {
+        return Ok(Some(format!(
+          "Unknown time zone: {}, expected an IANA name like America/New_York",
+          name
+        )))
+      }
+    },
+    (Some("reset"), None) => None,
+    _ => {
+      return Ok(Some(String::from(
+        "Usage: @BOT timezone [set <IANA name>|reset]",
+      )))
+    }
+  };
+
+  let mut db_instance = INSTANCE.write().unwrap();
+  db_instance.user_timezone_update(author, new_timezone.map(|timezone| timezone.name()))?;
+  Ok(Some(format!(
+    "Your time zone is now {}",
+    new_timezone.unwrap_or(date_parse::DEFAULT_TIMEZONE)
+  )))
+}
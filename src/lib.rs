@@ -0,0 +1,59 @@
+//! discord-db is a Rust Discord BOT.
+//!
+//! To simply runs this bot fill the credentials.json file at the root of your directory with your informations
+//!
+//! # Credential.json
+//! ```json
+//! {
+//!   "email": "your@email.io",
+//!   "password": "password",
+//!   "domain": "ssl0.ovh.net",
+//!   "token": "YOURDISCORDTOKEN"
+//! }
+//! ```
+//!
+//! And run `cargo run`
+//!
+//! This bot is compose of 2 modules:
+//!
+//!  *  [Core][core docs] Wich is the active connection with discord and manage the events.
+//!
+//!  *  [Features][features docs] The features that the bot do.
+//!
+//! The `rbot-admin` binary reuses the [Database][database docs] module to manage the bot state offline.
+//!
+//! [core docs]: core/index.html
+//! [features docs]: features/index.html
+//! [database docs]: database/index.html
+
+#![warn(clippy::all)]
+#![warn(unused_crate_dependencies)]
+#![warn(unused_extern_crates)]
+#![feature(extract_if)]
+#![feature(iter_map_windows)]
+#![feature(async_closure)]
+
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+pub mod macros;
+
+pub mod constants;
+pub mod core;
+pub mod database;
+pub mod features;
+
+use dotenv::dotenv;
+use std::env;
+
+/// Load the `.env` file and setup the logger, shared by the bot and the admin binaries.
+pub fn init() {
+  env::set_var("RUST_BACKTRACE", "full");
+  env::set_var("RUST_LOG", "rbot_discord,rocket");
+  dotenv().ok();
+  pretty_env_logger::init();
+}
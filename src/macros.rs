@@ -1,11 +1,12 @@
 macro_rules! db_add {
   ($name:ident,$new:ident, $result:ident, $table:ident ) => {
-    pub fn $name(&mut self, new: $new) {
+    pub fn $name(&mut self, new: $new) -> &$result {
       let result: $result = diesel::insert_into($table::table)
         .values(&new)
         .get_result(&mut self.get_connection())
         .expect("Error saving new $table");
       self.$table.push(result);
+      self.$table.last().unwrap()
     }
   };
 }
@@ -1,60 +1,8 @@
-//! discord-db is a Rust Discord BOT.
-//!
-//! To simply runs this bot fill the credentials.json file at the root of your directory with your informations
-//!
-//! # Credential.json
-//! ```json
-//! {
-//!   "email": "your@email.io",
-//!   "password": "password",
-//!   "domain": "ssl0.ovh.net",
-//!   "token": "YOURDISCORDTOKEN"
-//! }
-//! ```
-//!
-//! And run `cargo run`
-//!
-//! This bot is compose of 2 modules:
-//!
-//!  *  [Core][core docs] Wich is the active connection with discord and manage the events.
-//!
-//!  *  [Features][features docs] The features that the bot do.
-//!
-//!
-//! [core docs]: core/index.html
-//! [features docs]: features/index.html
-
-#![warn(clippy::all)]
-#![warn(unused_crate_dependencies)]
-#![warn(unused_extern_crates)]
-#![feature(extract_if)]
-#![feature(iter_map_windows)]
-#![feature(async_closure)]
-
-#[macro_use]
-extern crate lazy_static;
-#[macro_use]
-extern crate serde_derive;
-#[macro_use]
-extern crate diesel;
-#[macro_use]
-pub mod macros;
-
-mod constants;
-mod core;
-mod database;
-mod features;
-
-use dotenv::dotenv;
-use std::env;
+//! Entrypoint of the discord bot, see the [rbot_discord] library for the documentation.
 
 fn main() {
-  env::set_var("RUST_BACKTRACE", "full");
-  env::set_var("RUST_LOG", "rbot_discord,rocket");
-  dotenv().ok();
-  pretty_env_logger::init();
-
-  core::run();
+  rbot_discord::init();
+  rbot_discord::core::run();
 }
 
 #[test]